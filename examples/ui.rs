@@ -103,7 +103,7 @@ fn ui_example_system(
 
     if !*is_initialized {
         *is_initialized = true;
-        *rendered_texture_id = contexts.add_image(images.bevy_icon.clone_weak());
+        *rendered_texture_id = contexts.add_image(images.bevy_icon.clone_weak()).into();
     }
 
     let ctx = contexts.ctx_mut();
@@ -200,9 +200,11 @@ fn ui_example_system(
     if load || invert {
         // If an image is already added to the context, it'll return an existing texture id.
         if ui_state.inverted {
-            *rendered_texture_id = contexts.add_image(images.bevy_icon_inverted.clone_weak());
+            *rendered_texture_id = contexts
+                .add_image(images.bevy_icon_inverted.clone_weak())
+                .into();
         } else {
-            *rendered_texture_id = contexts.add_image(images.bevy_icon.clone_weak());
+            *rendered_texture_id = contexts.add_image(images.bevy_icon.clone_weak()).into();
         };
     }
     if remove {
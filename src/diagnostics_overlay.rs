@@ -0,0 +1,186 @@
+use crate::{EguiContext, EguiRenderOutput, EguiSet, EguiUserTextures};
+use bevy::{
+    app::{App, Plugin, PostUpdate},
+    diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
+    input::{keyboard::KeyCode, Input},
+    prelude::{Entity, IntoSystemConfigs, Query, Res, ResMut, Resource, With},
+    window::PrimaryWindow,
+};
+
+/// Adds a diagnostics overlay (FPS, frame time, Egui texture and primitive counts) to the
+/// primary window.
+///
+/// The overlay is drawn with a dedicated Egui context ([`EguiDiagnosticsOverlayContext`]), so it
+/// doesn't share memory, focus or input with the UI of the window, and its primitives are added
+/// on top of the window's ones after [`EguiSet::ProcessOutput`]. It's configured with the
+/// [`EguiDiagnosticsOverlay`] resource.
+///
+/// The plugin adds [`FrameTimeDiagnosticsPlugin`] unless it's already added, so an app that adds
+/// it itself has to do that before adding this plugin.
+pub struct EguiDiagnosticsOverlayPlugin;
+
+impl Plugin for EguiDiagnosticsOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin);
+        }
+        app.init_resource::<EguiDiagnosticsOverlay>()
+            .init_resource::<EguiDiagnosticsOverlayContext>();
+        #[cfg(feature = "render")]
+        app.add_systems(
+            PostUpdate,
+            diagnostics_overlay_system
+                .after(EguiSet::ProcessOutput)
+                .before(crate::update_egui_textures_system),
+        );
+        #[cfg(not(feature = "render"))]
+        app.add_systems(
+            PostUpdate,
+            diagnostics_overlay_system.after(EguiSet::ProcessOutput),
+        );
+    }
+}
+
+/// Configures the overlay of [`EguiDiagnosticsOverlayPlugin`].
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct EguiDiagnosticsOverlay {
+    /// Whether the overlay is shown (`false` by default).
+    pub visible: bool,
+    /// The key that toggles [`EguiDiagnosticsOverlay::visible`] (`F3` by default, [`None`]
+    /// disables toggling).
+    ///
+    /// The key is ignored while Egui of the primary window wants the keyboard input (e.g. when a
+    /// text edit has the focus).
+    pub toggle_key: Option<KeyCode>,
+    /// The corner (or the edge) of the window the overlay is anchored to (top right by default).
+    pub anchor: egui::Align2,
+}
+
+impl Default for EguiDiagnosticsOverlay {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            toggle_key: Some(KeyCode::F3),
+            anchor: egui::Align2::RIGHT_TOP,
+        }
+    }
+}
+
+/// The Egui context the overlay of [`EguiDiagnosticsOverlayPlugin`] is drawn with.
+///
+/// Its managed textures are added to the ones of the primary window with the ids offset by
+/// `1 << 63`, so they don't collide with the textures of the window's context.
+#[derive(Resource, Default)]
+pub struct EguiDiagnosticsOverlayContext {
+    ctx: egui::Context,
+    /// The window the textures of the context were sent to.
+    window: Option<Entity>,
+}
+
+/// Distance (in points) between the overlay and the window edges.
+const OVERLAY_MARGIN: f32 = 8.0;
+
+/// The offset of the overlay's managed texture ids (see [`EguiDiagnosticsOverlayContext`]).
+pub(crate) const OVERLAY_TEXTURE_ID_OFFSET: u64 = 1 << 63;
+
+fn overlay_texture_id(texture_id: egui::TextureId) -> egui::TextureId {
+    match texture_id {
+        egui::TextureId::Managed(id) => egui::TextureId::Managed(id + OVERLAY_TEXTURE_ID_OFFSET),
+        egui::TextureId::User(id) => egui::TextureId::User(id),
+    }
+}
+
+/// Toggles and draws the overlay of [`EguiDiagnosticsOverlayPlugin`].
+pub fn diagnostics_overlay_system(
+    mut overlay: ResMut<EguiDiagnosticsOverlay>,
+    mut overlay_context: ResMut<EguiDiagnosticsOverlayContext>,
+    keyboard_input: Res<Input<KeyCode>>,
+    diagnostics: Res<DiagnosticsStore>,
+    user_textures: Res<EguiUserTextures>,
+    #[cfg(feature = "render")] managed_textures: Option<Res<crate::EguiManagedTextures>>,
+    mut contexts: Query<(Entity, &mut EguiContext, &mut EguiRenderOutput), With<PrimaryWindow>>,
+) {
+    let Ok((window, mut window_ctx, mut render_output)) = contexts.get_single_mut() else {
+        return;
+    };
+    if window_ctx.is_frame_skipped() {
+        return;
+    }
+    let window_ctx = window_ctx.get_mut();
+    if overlay
+        .toggle_key
+        .is_some_and(|key| keyboard_input.just_pressed(key))
+        && !window_ctx.wants_keyboard_input()
+    {
+        overlay.visible = !overlay.visible;
+    }
+    if !overlay.visible {
+        return;
+    }
+    if overlay_context.window != Some(window) {
+        // The textures of the context have to be sent to the new window in full.
+        *overlay_context = EguiDiagnosticsOverlayContext {
+            window: Some(window),
+            ..Default::default()
+        };
+    }
+
+    let diagnostic = |id| {
+        diagnostics
+            .get(id)
+            .and_then(|diagnostic| diagnostic.smoothed())
+    };
+    let fps = diagnostic(FrameTimeDiagnosticsPlugin::FPS);
+    let frame_time = diagnostic(FrameTimeDiagnosticsPlugin::FRAME_TIME);
+    let texture_count = user_textures.textures.len();
+    #[cfg(feature = "render")]
+    let texture_count = texture_count
+        + managed_textures.map_or(0, |managed_textures| {
+            managed_textures
+                .keys()
+                .filter(|&&(texture_window, texture_id)| {
+                    texture_window == window && texture_id < OVERLAY_TEXTURE_ID_OFFSET
+                })
+                .count()
+        });
+    let primitive_count = render_output.paint_jobs.len();
+
+    let format = |value: Option<f64>, precision: usize| {
+        value.map_or_else(|| "-".to_owned(), |value| format!("{value:.precision$}"))
+    };
+    let ctx = &overlay_context.ctx;
+    ctx.begin_frame(egui::RawInput {
+        screen_rect: Some(window_ctx.screen_rect()),
+        pixels_per_point: Some(window_ctx.pixels_per_point()),
+        max_texture_side: Some(window_ctx.input(|input| input.max_texture_side)),
+        ..Default::default()
+    });
+    egui::Area::new("bevy_egui_diagnostics_overlay")
+        .anchor(overlay.anchor, -overlay.anchor.to_sign() * OVERLAY_MARGIN)
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.label(format!("FPS: {}", format(fps, 0)));
+                ui.label(format!("Frame time: {} ms", format(frame_time, 2)));
+                ui.label(format!("Egui textures: {texture_count}"));
+                ui.label(format!("Egui primitives: {primitive_count}"));
+            });
+        });
+    let full_output = ctx.end_frame();
+
+    let mut paint_jobs = ctx.tessellate(full_output.shapes);
+    for clipped_primitive in &mut paint_jobs {
+        if let egui::epaint::Primitive::Mesh(mesh) = &mut clipped_primitive.primitive {
+            mesh.texture_id = overlay_texture_id(mesh.texture_id);
+        }
+    }
+    render_output.paint_jobs.extend(paint_jobs);
+    let egui::TexturesDelta { set, free } = full_output.textures_delta;
+    render_output.textures_delta.append(egui::TexturesDelta {
+        set: set
+            .into_iter()
+            .map(|(texture_id, image_delta)| (overlay_texture_id(texture_id), image_delta))
+            .collect(),
+        free: free.into_iter().map(overlay_texture_id).collect(),
+    });
+}
@@ -1,6 +1,8 @@
 struct Transform {
     scale: vec2<f32>,
     translation: vec2<f32>,
+    // Linear RGBA, isn't premultiplied.
+    tint: vec4<f32>,
 }
 
 struct VertexInput {
@@ -13,11 +15,17 @@ struct VertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) color: vec4<f32>,
     @location(1) uv: vec2<f32>,
+    // Position in the window, from (0, 0) at the top left corner to (1, 1) at the bottom right one.
+    @location(2) mask_uv: vec2<f32>,
 }
 
 @group(0) @binding(0) var<uniform> transform: Transform;
 @group(1) @binding(0) var image_texture: texture_2d<f32>;
 @group(1) @binding(1) var image_sampler: sampler;
+#ifdef MASK
+@group(2) @binding(0) var mask_texture: texture_2d<f32>;
+@group(2) @binding(1) var mask_sampler: sampler;
+#endif
 
 fn linear_from_srgb(srgb: vec3<f32>) -> vec3<f32> {
     let cutoff = srgb < vec3<f32>(0.04045);
@@ -29,15 +37,35 @@ fn linear_from_srgb(srgb: vec3<f32>) -> vec3<f32> {
 @vertex
 fn vs_main(in: VertexInput) -> VertexOutput {
     let position = in.position * transform.scale + transform.translation;
-    let color = vec4<f32>(linear_from_srgb(in.color.rgb), in.color.a);
-    return VertexOutput(vec4<f32>(position, 0.0, 1.0), color, in.uv);
+    // Vertex colors are premultiplied, so the tint's alpha scales the RGB channels as well.
+    let tint = vec4<f32>(transform.tint.rgb * transform.tint.a, transform.tint.a);
+    let color = vec4<f32>(linear_from_srgb(in.color.rgb), in.color.a) * tint;
+    let mask_uv = vec2<f32>(position.x + 1.0, 1.0 - position.y) * 0.5;
+    return VertexOutput(vec4<f32>(position, 0.0, 1.0), color, in.uv, mask_uv);
+}
+
+// Goes from blue (0.0) through cyan, green and yellow to red (1.0).
+fn heatmap(value: f32) -> vec3<f32> {
+    let t = 4.0 * clamp(value, 0.0, 1.0);
+    return clamp(vec3<f32>(t - 2.0, 2.0 - abs(t - 2.0), 2.0 - t), vec3<f32>(0.0), vec3<f32>(1.0));
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+#ifdef COLORMAP_GRAYSCALE
+    let texture_color = vec4<f32>(vec3<f32>(textureSample(image_texture, image_sampler, in.uv).r), 1.0);
+#else ifdef COLORMAP_HEATMAP
+    let texture_color = vec4<f32>(heatmap(textureSample(image_texture, image_sampler, in.uv).r), 1.0);
+#else
     let texture_color = textureSample(image_texture, image_sampler, in.uv);
+#endif
     // This assumes that texture images are not premultiplied.
     let color = in.color * vec4<f32>(texture_color.rgb * texture_color.a, texture_color.a);
+#ifdef MASK
+    if textureSample(mask_texture, mask_sampler, in.mask_uv).a < 0.5 {
+        discard;
+    }
+#endif
 
     return color;
 }
@@ -1,13 +1,14 @@
 use crate::{
     render_systems::{
-        EguiPipelines, EguiTextureBindGroups, EguiTextureId, EguiTransform, EguiTransforms,
+        EguiMaskBindGroups, EguiPipelines, EguiTextureBindGroups, EguiTextureId, EguiTransforms,
+        ExtractedEguiFrames,
     },
-    EguiRenderOutput, EguiSettings, WindowSize,
+    EguiColormap, EguiMask, EguiSettings, EguiUserTextures,
 };
 use bevy::{
     core::cast_slice,
     ecs::world::{FromWorld, World},
-    prelude::{Entity, Handle, Resource},
+    prelude::{Entity, Handle, Resource, Vec4},
     render::{
         render_graph::{Node, NodeRunError, RenderGraphContext},
         render_resource::{
@@ -51,7 +52,7 @@ impl FromWorld for EguiPipeline {
                     ty: BindingType::Buffer {
                         ty: BufferBindingType::Uniform,
                         has_dynamic_offset: true,
-                        min_binding_size: Some(EguiTransform::min_size()),
+                        min_binding_size: Some(<[Vec4; 2]>::min_size()),
                     },
                     count: None,
                 }],
@@ -92,18 +93,33 @@ impl FromWorld for EguiPipeline {
 pub struct EguiPipelineKey {
     /// Texture format of a window's swap chain to render to.
     pub texture_format: TextureFormat,
+    /// Colormap applied to sampled texture colors.
+    pub colormap: EguiColormap,
+    /// Whether fragments are discarded outside of the window's [`EguiMask`].
+    pub masked: bool,
 }
 
 impl SpecializedRenderPipeline for EguiPipeline {
     type Key = EguiPipelineKey;
 
     fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        let mut fragment_shader_defs = match key.colormap {
+            EguiColormap::Rgba => Vec::new(),
+            EguiColormap::Grayscale => vec!["COLORMAP_GRAYSCALE".into()],
+            EguiColormap::Heatmap => vec!["COLORMAP_HEATMAP".into()],
+        };
+        let mut layout = vec![
+            self.transform_bind_group_layout.clone(),
+            self.texture_bind_group_layout.clone(),
+        ];
+        if key.masked {
+            fragment_shader_defs.push("MASK".into());
+            // A mask is bound as a texture as well.
+            layout.push(self.texture_bind_group_layout.clone());
+        }
         RenderPipelineDescriptor {
             label: Some("egui render pipeline".into()),
-            layout: vec![
-                self.transform_bind_group_layout.clone(),
-                self.texture_bind_group_layout.clone(),
-            ],
+            layout,
             vertex: VertexState {
                 shader: EGUI_SHADER_HANDLE,
                 shader_defs: Vec::new(),
@@ -119,7 +135,7 @@ impl SpecializedRenderPipeline for EguiPipeline {
             },
             fragment: Some(FragmentState {
                 shader: EGUI_SHADER_HANDLE,
-                shader_defs: Vec::new(),
+                shader_defs: fragment_shader_defs,
                 entry_point: "fs_main".into(),
                 targets: vec![Some(ColorTargetState {
                     format: key.texture_format,
@@ -150,14 +166,33 @@ impl SpecializedRenderPipeline for EguiPipeline {
     }
 }
 
+/// A draw call of [`EguiNode`].
 #[derive(Debug)]
-struct DrawCommand {
-    vertices_count: usize,
-    egui_texture: EguiTextureId,
-    clipping_zone: (u32, u32, u32, u32), // x, y, w, h
+pub struct DrawCommand {
+    /// Number of indices to draw, starting right after the ones of the previous command.
+    pub vertices_count: usize,
+    /// Texture to bind, its bind group is stored in [`EguiTextureBindGroups`].
+    pub egui_texture: EguiTextureId,
+    /// Colormap of the pipeline to use.
+    pub colormap: EguiColormap,
+    /// Scissor rectangle in physical pixels: x, y, width, height.
+    pub clipping_zone: (u32, u32, u32, u32),
 }
 
 /// Egui render node.
+///
+/// The node never clears its target: the render pass loads the existing contents
+/// ([`LoadOp::Load`]) and blends Egui shapes over them, so anything rendered to the target
+/// before the node runs is preserved.
+///
+/// There's a node per window, which keeps its CPU-side vertex, index and draw command buffers
+/// between frames: they are cleared rather than reallocated, so a UI of a stable size doesn't
+/// cause per-frame allocations when its meshes are copied for rendering.
+///
+/// Custom render nodes can read the draw data of a window with
+/// `render_graph.get_node::<EguiNode>(node::egui_pass(window))` (see
+/// [`node::egui_pass`](crate::node::egui_pass)). The data is updated in [`Node::update`], so it's
+/// only valid during the render graph run.
 pub struct EguiNode {
     window_entity: Entity,
     vertex_data: Vec<u8>,
@@ -183,20 +218,60 @@ impl EguiNode {
             index_buffer: None,
         }
     }
+
+    /// The window the node renders to.
+    #[must_use]
+    pub fn window_entity(&self) -> Entity {
+        self.window_entity
+    }
+
+    /// Vertex data of the current frame, laid out as [`egui::epaint::Vertex`] values.
+    #[must_use]
+    pub fn vertex_data(&self) -> &[u8] {
+        &self.vertex_data
+    }
+
+    /// Index data of the current frame (`u32` indices, already offset for the shared vertex
+    /// buffer).
+    #[must_use]
+    pub fn index_data(&self) -> &[u8] {
+        &self.index_data
+    }
+
+    /// The GPU vertex buffer, which [`vertex_data`](Self::vertex_data) is written into.
+    #[must_use]
+    pub fn vertex_buffer(&self) -> Option<&Buffer> {
+        self.vertex_buffer.as_ref()
+    }
+
+    /// The GPU index buffer, which [`index_data`](Self::index_data) is written into.
+    #[must_use]
+    pub fn index_buffer(&self) -> Option<&Buffer> {
+        self.index_buffer.as_ref()
+    }
+
+    /// Draw calls of the current frame, in the painting order.
+    #[must_use]
+    pub fn draw_commands(&self) -> &[DrawCommand] {
+        &self.draw_commands
+    }
 }
 
 impl Node for EguiNode {
     fn update(&mut self, world: &mut World) {
-        let mut window_sizes = world.query::<(&WindowSize, &mut EguiRenderOutput)>();
-
-        let Ok((window_size, mut render_output)) = window_sizes.get_mut(world, self.window_entity)
-        else {
+        let mut extracted_frames = world.resource_mut::<ExtractedEguiFrames>();
+        let Some(frame) = extracted_frames.get_mut(&self.window_entity) else {
             return;
         };
-        let window_size = *window_size;
-        let paint_jobs = std::mem::take(&mut render_output.paint_jobs);
+        self.draw_commands.clear();
+        if frame.frame_skipped {
+            return;
+        }
+        let window_size = frame.window_size;
+        let paint_jobs = std::mem::take(&mut frame.paint_jobs);
 
         let egui_settings = &world.get_resource::<EguiSettings>().unwrap();
+        let user_textures = world.resource::<EguiUserTextures>();
 
         let render_device = world.get_resource::<RenderDevice>().unwrap();
 
@@ -204,10 +279,10 @@ impl Node for EguiNode {
         if window_size.physical_width == 0.0 || window_size.physical_height == 0.0 {
             return;
         }
+        let round_to_pixel = egui_settings.round_to_pixel && can_round_to_pixel(scale_factor);
 
         let mut index_offset = 0;
 
-        self.draw_commands.clear();
         self.vertex_data.clear();
         self.index_data.clear();
 
@@ -223,6 +298,11 @@ impl Node for EguiNode {
                 }
             };
 
+            // Shapes can't be painted outside of `EguiSettings::viewport_rect`.
+            let clip_rect = match egui_settings.viewport_rect {
+                Some(viewport_rect) => clip_rect.intersect(viewport_rect),
+                None => *clip_rect,
+            };
             let (x, y, w, h) = (
                 (clip_rect.min.x * scale_factor).round() as u32,
                 (clip_rect.min.y * scale_factor).round() as u32,
@@ -238,15 +318,37 @@ impl Node for EguiNode {
                 continue;
             }
 
-            self.vertex_data
-                .extend_from_slice(cast_slice::<_, u8>(mesh.vertices.as_slice()));
-            let indices_with_offset = mesh
-                .indices
-                .iter()
-                .map(|i| i + index_offset)
-                .collect::<Vec<_>>();
+            let texture_options = match mesh.texture_id {
+                egui::TextureId::User(id) => user_textures.options.get(&id),
+                egui::TextureId::Managed(_) => None,
+            };
+            let colormap = texture_options.map_or(EguiColormap::Rgba, |options| options.colormap);
+            let uv_options = texture_options.filter(|options| options.flips_uv());
+            if round_to_pixel || uv_options.is_some() {
+                for vertex in &mesh.vertices {
+                    let mut vertex = *vertex;
+                    if round_to_pixel {
+                        vertex.pos = round_to_pixel_pos(vertex.pos, scale_factor);
+                    }
+                    if let Some(uv_options) = uv_options {
+                        vertex.uv = uv_options.map_uv(vertex.uv);
+                    }
+                    self.vertex_data
+                        .extend_from_slice(cast_slice::<_, u8>(&[vertex]));
+                }
+            } else {
+                self.vertex_data
+                    .extend_from_slice(cast_slice::<_, u8>(mesh.vertices.as_slice()));
+            }
+            // Offset indices are written directly into the reused buffer, avoiding a temporary
+            // allocation per mesh.
             self.index_data
-                .extend_from_slice(cast_slice(indices_with_offset.as_slice()));
+                .reserve(mesh.indices.len() * std::mem::size_of::<u32>());
+            self.index_data.extend(
+                mesh.indices
+                    .iter()
+                    .flat_map(|index| (index + index_offset).to_ne_bytes()),
+            );
             index_offset += mesh.vertices.len() as u32;
 
             let texture_handle = match mesh.texture_id {
@@ -259,6 +361,7 @@ impl Node for EguiNode {
             self.draw_commands.push(DrawCommand {
                 vertices_count: mesh.indices.len(),
                 egui_texture: texture_handle,
+                colormap,
                 clipping_zone: (
                     x,
                     y,
@@ -302,6 +405,10 @@ impl Node for EguiNode {
         render_context: &mut RenderContext,
         world: &World,
     ) -> Result<(), NodeRunError> {
+        // Nothing is painted, or the frame is skipped (see `EguiSettings::render_when_unfocused`).
+        if self.draw_commands.is_empty() {
+            return Ok(());
+        }
         let egui_pipelines = &world.get_resource::<EguiPipelines>().unwrap().0;
         let pipeline_cache = world.get_resource::<PipelineCache>().unwrap();
 
@@ -351,14 +458,27 @@ impl Node for EguiNode {
                     depth_stencil_attachment: None,
                 });
 
-        let Some(pipeline_id) = egui_pipelines.get(&extracted_window.entity) else {
-            return Ok(());
+        let get_pipeline = |colormap| {
+            let pipeline_id = egui_pipelines.get(&(extracted_window.entity, colormap))?;
+            pipeline_cache.get_render_pipeline(*pipeline_id)
         };
-        let Some(pipeline) = pipeline_cache.get_render_pipeline(*pipeline_id) else {
+        let Some(pipeline) = get_pipeline(EguiColormap::Rgba) else {
             return Ok(());
         };
+        let mask_bind_group = match world.get::<EguiMask>(self.window_entity) {
+            Some(_) => match world
+                .resource::<EguiMaskBindGroups>()
+                .get(&self.window_entity)
+            {
+                Some(bind_group) => Some(bind_group),
+                // Egui isn't rendered unmasked while the mask image is loading.
+                None => return Ok(()),
+            },
+            None => None,
+        };
 
         render_pass.set_pipeline(pipeline);
+        let mut current_colormap = EguiColormap::Rgba;
         render_pass.set_vertex_buffer(0, *self.vertex_buffer.as_ref().unwrap().slice(..));
         render_pass.set_index_buffer(
             *self.index_buffer.as_ref().unwrap().slice(..),
@@ -368,6 +488,9 @@ impl Node for EguiNode {
         let transform_buffer_offset = egui_transforms.offsets[&self.window_entity];
         let transform_buffer_bind_group = &egui_transforms.bind_group.as_ref().unwrap().1;
         render_pass.set_bind_group(0, transform_buffer_bind_group, &[transform_buffer_offset]);
+        if let Some(mask_bind_group) = mask_bind_group {
+            render_pass.set_bind_group(2, mask_bind_group, &[]);
+        }
 
         let mut vertex_offset: u32 = 0;
         for draw_command in &self.draw_commands {
@@ -382,6 +505,16 @@ impl Node for EguiNode {
                     }
                 };
 
+                if draw_command.colormap != current_colormap {
+                    // A pipeline of a colormap gets compiled when it's used for the first time.
+                    let Some(pipeline) = get_pipeline(draw_command.colormap) else {
+                        vertex_offset += draw_command.vertices_count as u32;
+                        continue;
+                    };
+                    render_pass.set_pipeline(pipeline);
+                    current_colormap = draw_command.colormap;
+                }
+
                 render_pass.set_bind_group(1, texture_bind_group, &[]);
 
                 render_pass.set_scissor_rect(
@@ -453,3 +586,118 @@ pub(crate) fn color_image_as_bevy_image(
         )
     }
 }
+
+/// Whether vertex positions can be snapped to whole physical pixels at `scale_factor` (see
+/// [`EguiSettings::round_to_pixel`]).
+fn can_round_to_pixel(scale_factor: f32) -> bool {
+    scale_factor.fract() == 0.0
+}
+
+/// Snaps `pos` (in points) to the nearest whole physical pixel.
+fn round_to_pixel_pos(pos: egui::Pos2, scale_factor: f32) -> egui::Pos2 {
+    egui::pos2(
+        (pos.x * scale_factor).round() / scale_factor,
+        (pos.y * scale_factor).round() / scale_factor,
+    )
+}
+
+/// Returns a copy of `image` with a full mipmap chain, each level downsampled from the previous
+/// one with a box filter (in linear space for sRGB formats).
+///
+/// Returns `None` for images that aren't 2D, have several layers or mipmaps, or don't have an
+/// 8-bit RGBA or BGRA format.
+pub(crate) fn generate_mipmaps(image: &Image) -> Option<Image> {
+    let descriptor = &image.texture_descriptor;
+    let is_srgb = match descriptor.format {
+        TextureFormat::Rgba8Unorm | TextureFormat::Bgra8Unorm => false,
+        TextureFormat::Rgba8UnormSrgb | TextureFormat::Bgra8UnormSrgb => true,
+        _ => return None,
+    };
+    let Extent3d {
+        width,
+        height,
+        depth_or_array_layers,
+    } = descriptor.size;
+    if descriptor.dimension != TextureDimension::D2
+        || depth_or_array_layers != 1
+        || descriptor.mip_level_count != 1
+        || image.data.len() != width as usize * height as usize * 4
+    {
+        return None;
+    }
+
+    let to_linear = |value: u8, channel: usize| {
+        if is_srgb && channel < 3 {
+            egui::ecolor::linear_f32_from_gamma_u8(value)
+        } else {
+            value as f32 / 255.0
+        }
+    };
+    let from_linear = |value: f32, channel: usize| {
+        if is_srgb && channel < 3 {
+            egui::ecolor::gamma_u8_from_linear_f32(value)
+        } else {
+            (value * 255.0).round() as u8
+        }
+    };
+
+    let mut data = image.data.clone();
+    let mut level_offset = 0;
+    let (mut level_width, mut level_height) = (width as usize, height as usize);
+    let mut mip_level_count = 1;
+    while level_width > 1 || level_height > 1 {
+        let (next_width, next_height) = ((level_width / 2).max(1), (level_height / 2).max(1));
+        let mut next_level = Vec::with_capacity(next_width * next_height * 4);
+        for y in 0..next_height {
+            for x in 0..next_width {
+                // Odd sides leave the last row or column out of the 2x2 box.
+                let xs = [2 * x, (2 * x + 1).min(level_width - 1)];
+                let ys = [2 * y, (2 * y + 1).min(level_height - 1)];
+                for channel in 0..4 {
+                    let sum: f32 = ys
+                        .iter()
+                        .flat_map(|&sy| xs.iter().map(move |&sx| (sx, sy)))
+                        .map(|(sx, sy)| {
+                            let index = level_offset + (sy * level_width + sx) * 4 + channel;
+                            to_linear(data[index], channel)
+                        })
+                        .sum();
+                    next_level.push(from_linear(sum / 4.0, channel));
+                }
+            }
+        }
+        level_offset = data.len();
+        data.extend(next_level);
+        (level_width, level_height) = (next_width, next_height);
+        mip_level_count += 1;
+    }
+
+    let mut mipmapped_image = image.clone();
+    mipmapped_image.data = data;
+    mipmapped_image.texture_descriptor.mip_level_count = mip_level_count;
+    mipmapped_image.texture_view_descriptor = None;
+    Some(mipmapped_image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_to_pixel() {
+        let pos = egui::pos2(10.3, 10.8);
+        assert_eq!(round_to_pixel_pos(pos, 1.0), egui::pos2(10.0, 11.0));
+        // Half points are whole pixels at the scale factor of 2.
+        assert_eq!(round_to_pixel_pos(pos, 2.0), egui::pos2(10.5, 11.0));
+        assert_eq!(
+            round_to_pixel_pos(egui::pos2(0.24, -0.26), 2.0),
+            egui::pos2(0.0, -0.5)
+        );
+
+        assert!(can_round_to_pixel(1.0));
+        assert!(can_round_to_pixel(2.0));
+        // Fractional scale factors (e.g. a window scale factor of 1.25) aren't snapped to.
+        assert!(!can_round_to_pixel(1.25));
+        assert!(!can_round_to_pixel(1.5));
+    }
+}
@@ -11,6 +11,7 @@
 //! - Clipboard (web support is limited to the same window, see [rust-windowing/winit#1829](https://github.com/rust-windowing/winit/issues/1829))
 //! - Opening URLs
 //! - Multiple windows support (see [./examples/two_windows.rs](https://github.com/mvlabat/bevy_egui/blob/v0.20.1/examples/two_windows.rs))
+//! - Diagnostics overlay with FPS and frame time (the optional `diagnostics_overlay` feature)
 //!
 //! `bevy_egui` can be compiled with using only `bevy` and `egui` as dependencies: `manage_clipboard` and `open_url` features,
 //! that require additional crates, can be disabled.
@@ -50,52 +51,88 @@
 //!
 //! - [`bevy-inspector-egui`](https://github.com/jakobhellermann/bevy-inspector-egui)
 
+/// A diagnostics overlay drawn with Egui (requires the `diagnostics_overlay` feature).
+#[cfg(feature = "diagnostics_overlay")]
+pub mod diagnostics_overlay;
 /// Plugin systems for the render app.
+///
+/// Together with [`egui_node`], this module is the extension point for custom render passes. The
+/// resources meant to be read by other crates are [`render_systems::EguiTextureBindGroups`]
+/// (texture bind groups, valid after [`RenderSet::Queue`]), [`render_systems::EguiTextureViews`]
+/// (the texture views behind them, valid after [`RenderSet::Queue`] as well),
+/// [`render_systems::ExtractedEguiFrames`] (Egui output of all windows),
+/// [`render_systems::EguiTransforms`], [`render_systems::EguiPipelines`] and the draw data of
+/// [`egui_node::EguiNode`]. Their layout follows the `bevy_egui` version and can change in
+/// a breaking release, the systems are implementation details. User textures can be backed by
+/// GPU textures managed elsewhere with [`render_systems::EguiTextureProvider`].
+#[cfg(feature = "render")]
 pub mod render_systems;
 /// Plugin systems.
 pub mod systems;
 
 /// Egui render node.
+#[cfg(feature = "render")]
 pub mod egui_node;
 
 pub use egui;
 
+use crate::systems::*;
+#[cfg(feature = "render")]
 use crate::{
     egui_node::{EguiPipeline, EGUI_SHADER_HANDLE},
-    render_systems::{EguiTransforms, ExtractedEguiManagedTextures},
-    systems::*,
+    render_systems::{
+        EguiTransforms, EguiUserTextureSamplers, ExtractedEguiFrames, ExtractedEguiManagedTextures,
+    },
 };
 #[cfg(all(feature = "manage_clipboard", not(target_arch = "wasm32")))]
 use arboard::Clipboard;
+#[cfg(feature = "render")]
+use bevy::{
+    app::Last,
+    asset::{load_internal_asset, AssetEvent},
+    ecs::{
+        change_detection::DetectChangesMut,
+        system::{Local, Res},
+    },
+    prelude::Shader,
+    render::{
+        extract_component::{ExtractComponent, ExtractComponentPlugin},
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        render_resource::SpecializedRenderPipelines,
+        renderer::RenderDevice,
+        ExtractSchedule, Render, RenderApp, RenderSet,
+    },
+    utils::HashSet,
+};
 use bevy::{
-    app::{App, Last, Plugin, PostUpdate, PreStartup, PreUpdate},
-    asset::{load_internal_asset, AssetEvent, Assets, Handle},
+    app::{App, Plugin, PostUpdate, PreStartup, PreUpdate},
+    asset::{AssetId, Assets, Handle},
     ecs::{
         event::EventReader,
-        query::{QueryEntityError, WorldQuery},
+        query::{Has, QueryEntityError, WorldQuery},
         schedule::apply_deferred,
-        system::{Res, ResMut, SystemParam},
+        system::{ResMut, SystemParam},
     },
     input::InputSystem,
     log,
+    math::{Ray, Vec2},
     prelude::{
-        Added, Commands, Component, Deref, DerefMut, Entity, IntoSystemConfigs, Query, Resource,
-        Shader, SystemSet, With, Without,
+        Added, Camera, Color, Commands, Component, Deref, DerefMut, Entity, Event, GlobalTransform,
+        IntoSystemConfigs, Query, Resource, SystemSet, With,
     },
     reflect::Reflect,
     render::{
-        extract_component::{ExtractComponent, ExtractComponentPlugin},
-        extract_resource::{ExtractResource, ExtractResourcePlugin},
-        render_resource::SpecializedRenderPipelines,
-        texture::{Image, ImageAddressMode, ImageSampler, ImageSamplerDescriptor},
-        ExtractSchedule, Render, RenderApp, RenderSet,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::{
+            Image, ImageAddressMode, ImageSampler, ImageSamplerBorderColor, ImageSamplerDescriptor,
+        },
     },
     utils::HashMap,
-    window::{PrimaryWindow, Window},
+    window::{PrimaryWindow, Window, WindowClosed, WindowTheme},
 };
-use std::borrow::Cow;
 #[cfg(all(feature = "manage_clipboard", not(target_arch = "wasm32")))]
 use std::cell::{RefCell, RefMut};
+use std::{borrow::Cow, collections::BTreeMap, ops::Deref};
 #[cfg(all(feature = "manage_clipboard", not(target_arch = "wasm32")))]
 use thread_local::ThreadLocal;
 
@@ -103,7 +140,8 @@ use thread_local::ThreadLocal;
 pub struct EguiPlugin;
 
 /// A resource for storing global UI settings.
-#[derive(Clone, Debug, Resource, ExtractResource, Reflect)]
+#[derive(Clone, Debug, Resource, Reflect)]
+#[cfg_attr(feature = "render", derive(ExtractResource))]
 pub struct EguiSettings {
     /// Global scale factor for Egui widgets (`1.0` by default).
     ///
@@ -127,14 +165,132 @@ pub struct EguiSettings {
     /// Defaults to linear and clamped to edge
     #[reflect(ignore)]
     pub sampler_descriptor: ImageSampler,
+    /// Delay (in seconds) after which a held key starts repeating (`0.5` by default).
+    ///
+    /// Repeated key events are synthesized only if the OS doesn't send them itself.
+    pub key_repeat_delay: f32,
+    /// Interval (in seconds) between synthesized repeated key events (`0.033` by default).
+    pub key_repeat_interval: f32,
+    /// Snaps vertex positions to whole physical pixels to make text crisper (`false` by default).
+    ///
+    /// Is applied only if the resulting scale factor (the window one multiplied by
+    /// [`EguiSettings::scale_factor`]) is a whole number, as snapping at fractional scale factors
+    /// makes shapes jitter while zooming.
+    pub round_to_pixel: bool,
+    /// Overrides the largest texture side Egui is allowed to allocate (`None` by default).
+    ///
+    /// By default, the limit of the render device is used (see
+    /// [`EguiRenderCapabilities::max_texture_side`]). The override can only lower it: if it
+    /// exceeds the device limit, the latter is used. Values below [`MIN_MAX_TEXTURE_SIDE`] are
+    /// raised to it.
+    ///
+    /// This also bounds the font atlas, which is at most `max_texture_side` (and 8192) pixels on
+    /// each side. When the glyphs don't fit, a warning is logged (see
+    /// [`EguiFontAtlasInfo::overflowed`]).
+    pub max_texture_side: Option<usize>,
+    /// Whether scrolling with Ctrl (or Cmd on macOS) held is sent to Egui as zooming
+    /// ([`egui::Event::Zoom`]) instead of scrolling (`true` by default).
+    ///
+    /// Touchpad pinch gestures are sent as zooming regardless of this setting.
+    pub zoom_with_ctrl_scroll: bool,
+    /// Size (in physical pixels) of the feathering Egui anti-aliases shape edges with (`1.0` by
+    /// default, as in Egui).
+    ///
+    /// `0.0` disables feathering, making edges crisp; larger values make them smoother (and
+    /// blurrier). Is applied to the [`egui::epaint::TessellationOptions`] of all contexts when
+    /// their shapes are tessellated, overriding the feathering options set on a context directly.
+    /// Windows with the [`EguiFeathering`] component use its size instead. Text isn't affected.
+    pub feathering_size_in_pixels: f32,
+    /// Whether Egui frames of unfocused windows are run (`true` by default).
+    ///
+    /// If `false`, the context of an unfocused window doesn't begin and end frames, its shapes
+    /// aren't tessellated and its Egui render pass is skipped, so Egui isn't drawn into the
+    /// window until it's focused again (the output of the last frame is kept in
+    /// [`EguiRenderOutput`], though), and its input is dropped. Windows with the [`EguiRenderWhenUnfocused`] component are always updated.
+    /// UI systems still run, they can check [`EguiContext::is_frame_skipped`] to avoid drawing
+    /// into a skipped context (whatever is drawn is discarded once the window is focused again).
+    pub render_when_unfocused: bool,
+    /// Confines Egui to a rectangle of the windows, in points (`None` by default).
+    ///
+    /// The rectangle becomes Egui's [`egui::RawInput::screen_rect`], so panels and windows are
+    /// laid out and clipped inside it. Pointer and touch events outside the rectangle aren't sent
+    /// to Egui (the pointer is treated as having left the window), so they pass through to the
+    /// app.
+    #[reflect(ignore)]
+    pub viewport_rect: Option<egui::Rect>,
+    /// Distance (in points) a touch has to move before Egui sees it as a drag (`0.0`, disabled,
+    /// by default).
+    ///
+    /// Moves of the touch that emulates the pointer are withheld until it leaves the deadzone,
+    /// and a touch that ends inside of it is released where it was pressed, so that taps on
+    /// cheap touch panels reliably click buttons. The deadzone only applies during
+    /// [`EguiSettings::touch_tap_timeout`], so that slow drags still work.
+    pub touch_drag_deadzone: f32,
+    /// Time (in seconds) after the start of a touch during which
+    /// [`EguiSettings::touch_drag_deadzone`] applies (`0.5` by default).
+    pub touch_tap_timeout: f32,
+    /// Whether user textures whose images aren't loaded yet are drawn as a 1×1 white texture
+    /// (`true` by default).
+    ///
+    /// This way, an [`egui::Image`] with a size hint occupies and shows its area (tinted with
+    /// its tint color) while the image asset is loading, instead of leaving a hole. If `false`,
+    /// meshes with such textures aren't drawn until their images are loaded.
+    pub user_texture_fallback: bool,
+    /// Whether Egui visuals follow the light or dark theme of the OS (`false` by default).
+    ///
+    /// If enabled, a context gets [`egui::Visuals::dark`] or [`egui::Visuals::light`] whenever the
+    /// theme of its window changes ([`WindowThemeChanged`](bevy::window::WindowThemeChanged)),
+    /// replacing the current visuals. The theme a window is created with
+    /// ([`Window::window_theme`]) isn't applied, so visuals set by the app (e.g. in a startup
+    /// system) are kept until the OS theme is switched. The theme is tracked regardless of the
+    /// setting, see [`EguiContext::system_theme`]. Accent colors aren't reported by `winit`, so
+    /// they aren't applied.
+    pub follow_system_theme: bool,
+    /// Color that all Egui shapes are multiplied by when rendered (`Color::WHITE`, no tint, by
+    /// default).
+    ///
+    /// Dims or tints the whole UI at once, for example, to give a menu a disabled look while
+    /// a modal dialog is shown. The alpha makes the UI translucent.
+    pub global_tint: Color,
+    /// Removes control characters other than newlines and tabs from pasted text (`false` by
+    /// default).
+    ///
+    /// Line endings of pasted text are normalized to `\n` regardless of this setting.
+    #[cfg(feature = "manage_clipboard")]
+    pub strip_pasted_control_chars: bool,
+    /// Whether copied text is written to the system clipboard and pasted text is read from it
+    /// (`true` by default).
+    ///
+    /// If `false`, the system clipboard is never touched: copied text is only reported with
+    /// [`EguiCopyRequest`] events, and the text pasted with the paste shortcut is the one set with
+    /// [`EguiClipboard::set_paste_contents`].
+    #[cfg(feature = "manage_clipboard")]
+    pub use_system_clipboard: bool,
 }
 
 // Just to keep the PartialEq
 impl PartialEq for EguiSettings {
     fn eq(&self, other: &Self) -> bool {
-        let eq = self.scale_factor == other.scale_factor;
+        let eq = self.scale_factor == other.scale_factor
+            && self.key_repeat_delay == other.key_repeat_delay
+            && self.key_repeat_interval == other.key_repeat_interval
+            && self.round_to_pixel == other.round_to_pixel
+            && self.max_texture_side == other.max_texture_side
+            && self.zoom_with_ctrl_scroll == other.zoom_with_ctrl_scroll
+            && self.feathering_size_in_pixels == other.feathering_size_in_pixels
+            && self.render_when_unfocused == other.render_when_unfocused
+            && self.viewport_rect == other.viewport_rect
+            && self.touch_drag_deadzone == other.touch_drag_deadzone
+            && self.touch_tap_timeout == other.touch_tap_timeout
+            && self.user_texture_fallback == other.user_texture_fallback
+            && self.follow_system_theme == other.follow_system_theme
+            && self.global_tint == other.global_tint;
         #[cfg(feature = "open_url")]
         let eq = eq && self.default_open_url_target == other.default_open_url_target;
+        #[cfg(feature = "manage_clipboard")]
+        let eq = eq
+            && self.strip_pasted_control_chars == other.strip_pasted_control_chars
+            && self.use_system_clipboard == other.use_system_clipboard;
         eq && compare_descriptors(&self.sampler_descriptor, &other.sampler_descriptor)
     }
 }
@@ -161,6 +317,23 @@ impl Default for EguiSettings {
                 address_mode_v: ImageAddressMode::ClampToEdge,
                 ..ImageSamplerDescriptor::linear()
             }),
+            key_repeat_delay: 0.5,
+            key_repeat_interval: 0.033,
+            round_to_pixel: false,
+            max_texture_side: None,
+            zoom_with_ctrl_scroll: true,
+            feathering_size_in_pixels: 1.0,
+            render_when_unfocused: true,
+            viewport_rect: None,
+            touch_drag_deadzone: 0.0,
+            touch_tap_timeout: 0.5,
+            user_texture_fallback: true,
+            follow_system_theme: false,
+            global_tint: Color::WHITE,
+            #[cfg(feature = "manage_clipboard")]
+            strip_pasted_control_chars: false,
+            #[cfg(feature = "manage_clipboard")]
+            use_system_clipboard: true,
         }
     }
 }
@@ -180,6 +353,44 @@ impl EguiSettings {
     }
 }
 
+/// A time source that `bevy_egui` uses to advance Egui's clock (which drives animations).
+///
+/// Can be replaced with [`EguiClock::Fixed`] to make animations deterministic, for example,
+/// in tests.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Resource)]
+pub enum EguiClock {
+    /// Uses Bevy's real time (`Time<Real>`).
+    #[default]
+    Real,
+    /// Advances the time seen by Egui by exactly this amount of seconds every frame.
+    Fixed(f32),
+}
+
+/// The smallest texture side Egui works with, as its font atlas can't be narrower.
+///
+/// It's the smallest `max_texture_dimension_2d` limit of a render device that Egui is rendered
+/// with (see [`EguiRenderCapabilities::rendering_enabled`]), and lower values of
+/// [`EguiSettings::max_texture_side`] are raised to it.
+pub const MIN_MAX_TEXTURE_SIDE: usize = 1024;
+
+/// Capabilities of the render device that `bevy_egui` detects when the app is built.
+///
+/// The resource is inserted only if there's a render device (it's missing in headless mode).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Resource)]
+pub struct EguiRenderCapabilities {
+    /// The largest texture side supported by the device (`max_texture_dimension_2d`).
+    ///
+    /// It's passed to Egui as [`egui::RawInput::max_texture_side`], so that Egui never
+    /// allocates a font atlas that the device can't create.
+    pub max_texture_side: usize,
+    /// Whether Egui is rendered at all.
+    ///
+    /// It's `false` if the render device doesn't meet the minimum requirements of the Egui
+    /// pipeline (see [`MIN_MAX_TEXTURE_SIDE`]), the reason is logged as an error.
+    /// Egui contexts keep working in that case, so UI systems don't need to be disabled.
+    pub rendering_enabled: bool,
+}
+
 /// Is used for storing Egui context input..
 ///
 /// It gets reset during the [`EguiSet::ProcessInput`] system.
@@ -196,6 +407,7 @@ pub struct EguiClipboard {
     clipboard: ThreadLocal<Option<RefCell<Clipboard>>>,
     #[cfg(target_arch = "wasm32")]
     clipboard: String,
+    paste_contents: Option<String>,
 }
 
 #[cfg(feature = "manage_clipboard")]
@@ -211,6 +423,18 @@ impl EguiClipboard {
         self.get_contents_impl()
     }
 
+    /// Sets the text pasted with the paste shortcut while
+    /// [`EguiSettings::use_system_clipboard`] is `false` ([`None`] makes the shortcut do nothing).
+    pub fn set_paste_contents(&mut self, contents: Option<String>) {
+        self.paste_contents = contents;
+    }
+
+    /// Gets the text set with [`EguiClipboard::set_paste_contents`].
+    #[must_use]
+    pub fn paste_contents(&self) -> Option<&str> {
+        self.paste_contents.as_deref()
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     fn set_contents_impl(&self, contents: &str) {
         if let Some(mut clipboard) = self.get() {
@@ -243,7 +467,7 @@ impl EguiClipboard {
     }
 
     #[cfg(not(target_arch = "wasm32"))]
-    fn get(&self) -> Option<RefMut<Clipboard>> {
+    fn get(&self) -> Option<RefMut<'_, Clipboard>> {
         self.clipboard
             .get_or(|| {
                 Clipboard::new()
@@ -259,17 +483,58 @@ impl EguiClipboard {
 }
 
 /// Is used for storing Egui shapes and textures delta.
-#[derive(Component, Clone, Default, Debug, ExtractComponent)]
+///
+/// The output of all windows is extracted to the render world as
+/// [`render_systems::ExtractedEguiFrames`].
+#[derive(Component, Clone, Default, Debug)]
 pub struct EguiRenderOutput {
     /// Pairs of rectangles and paint commands.
     ///
-    /// The field gets populated during the [`EguiSet::ProcessOutput`] system (belonging to bevy's [`PostUpdate`]) and reset during `EguiNode::update`.
+    /// The field gets populated during the [`EguiSet::ProcessOutput`] system (belonging to bevy's [`PostUpdate`]).
     pub paint_jobs: Vec<egui::ClippedPrimitive>,
 
     /// The change in egui textures since last frame.
     pub textures_delta: egui::TexturesDelta,
 }
 
+impl EguiRenderOutput {
+    /// Returns the texture sampled by the top-most triangle under `pos` (in Egui points).
+    ///
+    /// Can be used for building picking tools (such as an eyedropper) over images displayed in
+    /// Egui: a user texture id can be mapped back to its image with
+    /// [`EguiUserTextures::image_handle`]. Keep in mind that most of Egui shapes (including
+    /// text and filled rectangles) sample the managed font texture.
+    ///
+    /// Is meant to be called after [`EguiSet::ProcessOutput`], when `paint_jobs` reflect the
+    /// last frame. Paint callbacks aren't considered.
+    #[must_use]
+    pub fn texture_id_at(&self, pos: egui::Pos2) -> Option<egui::TextureId> {
+        self.paint_jobs.iter().rev().find_map(|clipped_primitive| {
+            if !clipped_primitive.clip_rect.contains(pos) {
+                return None;
+            }
+            let egui::epaint::Primitive::Mesh(mesh) = &clipped_primitive.primitive else {
+                return None;
+            };
+            let is_under_pos = mesh.indices.chunks_exact(3).rev().any(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize].pos);
+                is_in_triangle(pos, a, b, c)
+            });
+            is_under_pos.then_some(mesh.texture_id)
+        })
+    }
+}
+
+fn is_in_triangle(pos: egui::Pos2, a: egui::Pos2, b: egui::Pos2, c: egui::Pos2) -> bool {
+    let edge = |from: egui::Pos2, to: egui::Pos2| {
+        (to - from).x * (pos - from).y - (to - from).y * (pos - from).x
+    };
+    let (ab, bc, ca) = (edge(a, b), edge(b, c), edge(c, a));
+    let has_negative = ab < 0.0 || bc < 0.0 || ca < 0.0;
+    let has_positive = ab > 0.0 || bc > 0.0 || ca > 0.0;
+    !(has_negative && has_positive)
+}
+
 /// Is used for storing Egui output.
 #[derive(Component, Clone, Default)]
 pub struct EguiOutput {
@@ -278,10 +543,93 @@ pub struct EguiOutput {
 }
 
 /// A component for storing `bevy_egui` context.
-#[derive(Clone, Component, Default, ExtractComponent)]
-pub struct EguiContext(egui::Context);
+#[derive(Clone, Component, Default)]
+#[cfg_attr(feature = "render", derive(ExtractComponent))]
+pub struct EguiContext {
+    ctx: egui::Context,
+    /// Whether the frame managed by [`EguiSet::BeginFrame`] and [`EguiSet::ProcessOutput`]
+    /// has begun but hasn't ended yet.
+    frame_in_progress: bool,
+    /// Whether the app has claimed the input (see [`EguiContexts::grab_input`]).
+    input_grabbed: bool,
+    /// Whether frames aren't run as the window is unfocused
+    /// (see [`EguiSettings::render_when_unfocused`]).
+    frame_skipped: bool,
+    /// Whether a managed frame has begun at least once, so the context knows the scale of its
+    /// window.
+    has_run_frames: bool,
+    /// Texture updates of the last ended frame (see [`EguiContexts::font_atlas_info`]).
+    textures_delta_stats: EguiTexturesDeltaStats,
+    /// How many times the font atlas has been fully uploaded.
+    font_atlas_version: u64,
+    /// Whether the font atlas overflowed in the last ended frame.
+    font_atlas_overflowed: bool,
+    /// The last known theme of the window (see [`EguiSettings::follow_system_theme`]).
+    system_theme: Option<WindowTheme>,
+}
+
+/// Number of textures Egui has changed in a frame, see [`egui::TexturesDelta`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EguiTexturesDeltaStats {
+    /// Textures that were allocated or fully replaced.
+    pub full_updates: usize,
+    /// Textures that were partially updated (e.g. glyphs added to the font atlas).
+    pub partial_updates: usize,
+    /// Textures that were freed.
+    pub freed: usize,
+}
+
+impl EguiTexturesDeltaStats {
+    fn new(textures_delta: &egui::TexturesDelta) -> Self {
+        let partial_updates = textures_delta
+            .set
+            .iter()
+            .filter(|(_, image_delta)| image_delta.pos.is_some())
+            .count();
+        Self {
+            full_updates: textures_delta.set.len() - partial_updates,
+            partial_updates,
+            freed: textures_delta.free.len(),
+        }
+    }
+}
+
+/// Font atlas state of a context, for debugging texture churn
+/// (see [`EguiContexts::font_atlas_info`]).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EguiFontAtlasInfo {
+    /// Size of the atlas in pixels.
+    pub size: [usize; 2],
+    /// Share of the atlas that is used, from `0.0` to `1.0`.
+    pub fill_ratio: f32,
+    /// How many times the atlas has been fully uploaded (allocated or reallocated).
+    pub version: u64,
+    /// Whether the glyphs of the last ended frame didn't fit into the atlas.
+    ///
+    /// Egui then reuses the space of other glyphs, so some text is drawn garbled until the atlas
+    /// is cleared and rebuilt when the next frame begins. If it keeps overflowing, the frame uses
+    /// more glyphs than the atlas can hold: use fewer fonts or sizes, or raise
+    /// [`EguiSettings::max_texture_side`].
+    pub overflowed: bool,
+    /// Texture updates of the last ended frame, of all the textures managed by Egui.
+    pub textures_delta: EguiTexturesDeltaStats,
+}
 
 impl EguiContext {
+    /// Wraps a context created elsewhere (e.g. shared with non-Bevy code or pre-seeded with
+    /// memory and fonts).
+    ///
+    /// Insert it into a window entity (when spawning it) to make `bevy_egui` drive that context
+    /// instead of creating its own. `bevy_egui` begins and ends the frames of the context, so it mustn't be run by
+    /// other code at the same time.
+    #[must_use]
+    pub fn new(ctx: egui::Context) -> Self {
+        Self {
+            ctx,
+            ..Default::default()
+        }
+    }
+
     /// Borrows the underlying Egui context immutably.
     ///
     /// Even though the mutable borrow isn't necessary, as the context is wrapped into `RwLock`,
@@ -294,7 +642,7 @@ impl EguiContext {
     #[cfg(feature = "immutable_ctx")]
     #[must_use]
     pub fn get(&self) -> &egui::Context {
-        &self.0
+        &self.ctx
     }
 
     /// Borrows the underlying Egui context mutably.
@@ -308,7 +656,29 @@ impl EguiContext {
     /// instead of busy-waiting.
     #[must_use]
     pub fn get_mut(&mut self) -> &mut egui::Context {
-        &mut self.0
+        &mut self.ctx
+    }
+
+    /// Whether the current frame isn't run as the window is unfocused
+    /// (see [`EguiSettings::render_when_unfocused`]).
+    ///
+    /// Is reset if a frame is run with [`EguiContexts::run`] instead.
+    #[must_use]
+    pub fn is_frame_skipped(&self) -> bool {
+        self.frame_skipped
+    }
+
+    /// Whether the input is withheld from the context (see [`EguiContexts::grab_input`]).
+    #[must_use]
+    pub fn is_input_grabbed(&self) -> bool {
+        self.input_grabbed
+    }
+
+    /// The light or dark theme of the window, if it's known
+    /// (see [`EguiSettings::follow_system_theme`]).
+    #[must_use]
+    pub fn system_theme(&self) -> Option<WindowTheme> {
+        self.system_theme
     }
 }
 
@@ -326,6 +696,8 @@ pub struct EguiContexts<'w, 's> {
         ),
         With<Window>,
     >,
+    render_outputs: Query<'w, 's, (&'static EguiInput, &'static mut EguiRenderOutput)>,
+    window_sizes: Query<'w, 's, &'static WindowSize>,
     user_textures: ResMut<'w, EguiUserTextures>,
 }
 
@@ -443,43 +815,261 @@ impl<'w, 's> EguiContexts<'w, 's> {
             })
     }
 
-    /// Can accept either a strong or a weak handle.
+    /// Converts a position in Egui points to physical pixels of a window.
     ///
-    /// You may want to pass a weak handle if you control removing texture assets in your
-    /// application manually and you don't want to bother with cleaning up textures in Egui.
+    /// Both the window scale factor and [`EguiSettings::scale_factor`] are taken into account, as
+    /// they're applied to the frame in progress (or the last ended one, if there's none). Returns
+    /// `None` if the window doesn't have an Egui context or the context hasn't run any frames yet
+    /// (i.e. the function is called before the first [`EguiSet::BeginFrame`]).
+    #[must_use]
+    pub fn egui_to_physical(&self, window: Entity, pos: egui::Pos2) -> Option<Vec2> {
+        let pixels_per_point = self.pixels_per_point(window)?;
+        Some(egui_to_physical_pos(pos, pixels_per_point))
+    }
+
+    /// Converts a position in physical pixels of a window to Egui points.
     ///
-    /// You'll want to pass a strong handle if a texture is used only in Egui and there are no
-    /// handle copies stored anywhere else.
-    pub fn add_image(&mut self, image: Handle<Image>) -> egui::TextureId {
-        self.user_textures.add_image(image)
+    /// This is the inverse of [`EguiContexts::egui_to_physical`].
+    #[must_use]
+    pub fn physical_to_egui(&self, window: Entity, pos: Vec2) -> Option<egui::Pos2> {
+        let pixels_per_point = self.pixels_per_point(window)?;
+        Some(physical_to_egui_pos(pos, pixels_per_point))
     }
 
-    /// Removes the image handle and an Egui texture id associated with it.
+    /// Returns a ray going from `camera` through a position in Egui points of a window.
+    ///
+    /// The window is expected to be the render target of the camera. If the camera has a custom
+    /// viewport, its offset is subtracted, so the position is still expected to be relative to
+    /// the top-left corner of the window (as everything in Egui is).
+    #[must_use]
+    pub fn egui_pos_to_world(
+        &self,
+        window: Entity,
+        camera: &Camera,
+        camera_transform: &GlobalTransform,
+        pos: egui::Pos2,
+    ) -> Option<Ray> {
+        let pixels_per_point = self.pixels_per_point(window)?;
+        let window_size = self.window_sizes.get(window).ok()?;
+        let viewport_min = camera
+            .logical_viewport_rect()
+            .map_or(Vec2::ZERO, |rect| rect.min);
+        let viewport_pos = egui_to_viewport_pos(
+            pos,
+            pixels_per_point,
+            window_size.scale_factor,
+            viewport_min,
+        );
+        camera.viewport_to_world(camera_transform, viewport_pos)
+    }
+
+    fn pixels_per_point(&self, window: Entity) -> Option<f32> {
+        // `EguiInput` is emptied when a frame begins, so the factor is taken from the context.
+        let (_window, ctx, _primary_window) = self.q.get(window).ok()?;
+        ctx.has_run_frames.then(|| ctx.ctx.pixels_per_point())
+    }
+
+    /// Whether the pointer is over any Egui area of a window (even if it doesn't interact with it).
+    ///
+    /// Returns `false` if the window doesn't have an Egui context. See
+    /// [`egui::Context::is_pointer_over_area`].
+    #[must_use]
+    pub fn is_pointer_over_area(&mut self, window: Entity) -> bool {
+        self.try_ctx_for_window_mut(window)
+            .is_some_and(|ctx| ctx.is_pointer_over_area())
+    }
+
+    /// Whether Egui is actively using the pointer in a window (e.g. a widget is being dragged).
+    ///
+    /// Returns `false` if the window doesn't have an Egui context. See
+    /// [`egui::Context::is_using_pointer`].
+    #[must_use]
+    pub fn is_using_pointer(&mut self, window: Entity) -> bool {
+        self.try_ctx_for_window_mut(window)
+            .is_some_and(|ctx| ctx.is_using_pointer())
+    }
+
+    /// Gives the keyboard focus to the widget `id` of a window, see
+    /// [`egui::Memory::request_focus`].
+    ///
+    /// Can be called both during the frame and between frames (e.g. from a system that runs a
+    /// tutorial step): a widget shown in the frame that is in progress (or in the next one) gets
+    /// focused. If no widget with the id is shown, Egui drops the focus at the end of the next
+    /// frame, so [`EguiContexts::focused_widget`] returns `None` afterwards. Does nothing if the
+    /// window doesn't have an Egui context.
+    pub fn request_focus(&mut self, window: Entity, id: egui::Id) {
+        if let Some(ctx) = self.try_ctx_for_window_mut(window) {
+            ctx.memory_mut(|memory| memory.request_focus(id));
+        }
+    }
+
+    /// Returns the widget of a window that has the keyboard focus, see [`egui::Memory::focus`].
+    ///
+    /// Returns `None` if the window doesn't have an Egui context.
+    #[must_use]
+    pub fn focused_widget(&mut self, window: Entity) -> Option<egui::Id> {
+        self.try_ctx_for_window_mut(window)?
+            .memory(|memory| memory.focus())
+    }
+
+    /// Scales the text of a window's context, independently of [`EguiSettings::scale_factor`]
+    /// (which scales the whole UI).
+    ///
+    /// The font sizes of all the text styles in the context's [`egui::Style`] are multiplied by
+    /// `factor` relative to the sizes they had at the scale of `1.0`, so the function can be
+    /// called with different factors at runtime without the scaling accumulating. Text styles
+    /// that get changed afterwards (e.g. with [`egui::Context::set_style`]) are treated as
+    /// unscaled: call the function again to re-apply the factor to them.
     #[track_caller]
-    pub fn remove_image(&mut self, image: &Handle<Image>) -> Option<egui::TextureId> {
-        self.user_textures.remove_image(image)
+    pub fn set_font_scale(&mut self, window: Entity, factor: f32) {
+        set_context_font_scale(self.ctx_for_window_mut(window), factor);
     }
 
-    /// Returns an associated Egui texture id.
+    /// Returns the factor set with [`EguiContexts::set_font_scale`] (`1.0` by default).
     #[must_use]
     #[track_caller]
-    pub fn image_id(&self, image: &Handle<Image>) -> Option<egui::TextureId> {
-        self.user_textures.image_id(image)
+    pub fn font_scale(&mut self, window: Entity) -> f32 {
+        context_font_scale(self.ctx_for_window_mut(window))
     }
-}
 
-/// A resource for storing `bevy_egui` mouse position.
-#[derive(Resource, Component, Default, Deref, DerefMut)]
-pub struct EguiMousePosition(pub Option<(Entity, egui::Vec2)>);
+    /// Withholds all input from the Egui context of a window until [`EguiContexts::release_input`]
+    /// is called, so that the app can own the pointer and the keyboard even while they're over
+    /// Egui (e.g. while placing an object with the mouse).
+    ///
+    /// While the input is grabbed, Egui receives no events: nothing is hovered, clicked or typed
+    /// into. Buttons and keys that Egui considers pressed when the input is grabbed are released,
+    /// so no widget stays pressed after the input is released.
+    #[track_caller]
+    pub fn grab_input(&mut self, window: Entity) {
+        self.ctx_component_for_window_mut(window).input_grabbed = true;
+    }
 
-/// A resource for storing `bevy_egui` user textures.
-#[derive(Clone, Resource, Default, ExtractResource)]
-pub struct EguiUserTextures {
-    textures: HashMap<Handle<Image>, u64>,
-    last_texture_id: u64,
-}
+    /// Gives the input back to the Egui context of a window (see [`EguiContexts::grab_input`]).
+    #[track_caller]
+    pub fn release_input(&mut self, window: Entity) {
+        self.ctx_component_for_window_mut(window).input_grabbed = false;
+    }
+
+    #[track_caller]
+    fn ctx_component_for_window_mut(&mut self, window: Entity) -> &mut EguiContext {
+        self.q
+            .get_mut(window)
+            .map(|(_window, ctx, _primary_window)| ctx.into_inner())
+            .unwrap_or_else(|_| panic!("`EguiContexts` was called for an uninitialized context (window {window:?}), make sure your system is run after [`EguiSet::InitContexts`] (or [`EguiStartupSet::InitContexts`] for startup systems)"))
+    }
+
+    /// Returns the state of the font atlas of a window's context.
+    ///
+    /// Egui grows the atlas as glyphs are added and uploads it again in full when it gets
+    /// reallocated, so a [`EguiFontAtlasInfo::version`] that keeps increasing means the atlas is
+    /// thrashing. The atlas image itself is available as the
+    /// [`EguiManagedTexture::color_image`] of [`egui::TextureId::default`] (`(window, 0)`) in
+    /// [`EguiManagedTextures`].
+    #[must_use]
+    #[track_caller]
+    pub fn font_atlas_info(&mut self, window: Entity) -> EguiFontAtlasInfo {
+        let ctx = self.ctx_component_for_window_mut(window);
+        let (size, fill_ratio) = ctx.get_mut().fonts(|fonts| {
+            let atlas = fonts.texture_atlas();
+            let atlas = atlas.lock();
+            (atlas.size(), atlas.fill_ratio())
+        });
+        EguiFontAtlasInfo {
+            size,
+            fill_ratio,
+            version: ctx.font_atlas_version,
+            overflowed: ctx.font_atlas_overflowed,
+            textures_delta: ctx.textures_delta_stats,
+        }
+    }
+
+    /// Runs `add_contents` with the Egui context of a window, even if it's called outside
+    /// the frame managed by `bevy_egui`.
+    ///
+    /// If the managed frame is in progress (i.e. the function is called after
+    /// [`EguiSet::BeginFrame`] and before [`EguiSet::ProcessOutput`]), `add_contents` just runs
+    /// as a part of it.
+    ///
+    /// Otherwise, a frame can only be run if the managed one is skipped in this tick (see
+    /// [`EguiSettings::render_when_unfocused`]), as running two frames in a tick would advance
+    /// Egui time twice and make Egui treat the windows of the first frame as hidden. In that case
+    /// a minimal frame without any input is begun and ended around `add_contents`, and its shapes
+    /// replace the last output of the window, which is useful for quick debug overlays drawn from
+    /// one-shot systems. The context isn't considered skipped afterwards
+    /// ([`EguiContext::is_frame_skipped`]), so only one such frame runs per tick. Platform output
+    /// (cursor icon, copied text, etc.) of the frame is ignored.
+    ///
+    /// If neither is the case (the managed frame has already ended or hasn't begun yet),
+    /// `add_contents` isn't run, and a warning is logged.
+    #[track_caller]
+    pub fn run(&mut self, window: Entity, add_contents: impl FnOnce(&egui::Context)) {
+        let mut ctx = self
+            .q
+            .get_mut(window)
+            .map(|(_window, ctx, _primary_window)| ctx)
+            .unwrap_or_else(|_| panic!("`EguiContexts::run` was called for an uninitialized context (window {window:?}), make sure your system is run after [`EguiSet::InitContexts`] (or [`EguiStartupSet::InitContexts`] for startup systems)"));
+        if ctx.frame_in_progress {
+            add_contents(ctx.get_mut());
+            return;
+        }
+        if !ctx.frame_skipped {
+            log::warn!("`EguiContexts::run` was called outside the Egui frame of window {window:?}, which isn't skipped, ignoring the call");
+            return;
+        }
+
+        let Ok((egui_input, mut render_output)) = self.render_outputs.get_mut(window) else {
+            return;
+        };
+        ctx.frame_skipped = false;
+        let ctx = ctx.get_mut();
+        ctx.begin_frame(egui::RawInput {
+            screen_rect: egui_input.screen_rect,
+            pixels_per_point: egui_input.pixels_per_point,
+            max_texture_side: egui_input.max_texture_side,
+            predicted_dt: egui_input.predicted_dt,
+            ..Default::default()
+        });
+        add_contents(ctx);
+        let full_output = ctx.end_frame();
+        render_output.paint_jobs = ctx.tessellate(full_output.shapes);
+        render_output
+            .textures_delta
+            .append(full_output.textures_delta);
+    }
+
+    /// Same as [`EguiContexts::run`], but catches a panic in `add_contents` instead of letting
+    /// it bring down the app.
+    ///
+    /// The panic is logged as an error and `false` is returned. The frame is still ended as
+    /// usual, so the UI recovers on the next frame (widgets that didn't get shown because of the
+    /// panic just disappear for one frame). Meant for catching bugs in UI code during
+    /// development, as it has no effect if the app is compiled with `panic = "abort"` (which is
+    /// always the case for `wasm32` targets).
+    #[track_caller]
+    pub fn run_catching_panics(
+        &mut self,
+        window: Entity,
+        add_contents: impl FnOnce(&egui::Context),
+    ) -> bool {
+        let mut succeeded = true;
+        self.run(window, |ctx| {
+            let result =
+                std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| add_contents(ctx)));
+            if let Err(payload) = result {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .copied()
+                    .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                    .unwrap_or("unknown panic payload");
+                log::error!(
+                    "A panic occurred while building Egui UI (window {window:?}): {message}"
+                );
+                succeeded = false;
+            }
+        });
+        succeeded
+    }
 
-impl EguiUserTextures {
     /// Can accept either a strong or a weak handle.
     ///
     /// You may want to pass a weak handle if you control removing texture assets in your
@@ -487,88 +1077,803 @@ impl EguiUserTextures {
     ///
     /// You'll want to pass a strong handle if a texture is used only in Egui and there are no
     /// handle copies stored anywhere else.
-    pub fn add_image(&mut self, image: Handle<Image>) -> egui::TextureId {
-        let id = *self.textures.entry(image.clone()).or_insert_with(|| {
-            let id = self.last_texture_id;
-            log::debug!("Add a new image (id: {}, handle: {:?})", id, image);
-            self.last_texture_id += 1;
-            id
-        });
-        egui::TextureId::User(id)
+    pub fn add_image(&mut self, image: Handle<Image>) -> BevyEguiTextureId {
+        self.user_textures.add_image(image)
+    }
+
+    /// Same as [`EguiContexts::add_image`], but also sets Bevy-side options of the texture.
+    ///
+    /// If the image is already added, its options are replaced.
+    pub fn add_image_with_options(
+        &mut self,
+        image: Handle<Image>,
+        options: EguiUserTextureOptions,
+    ) -> BevyEguiTextureId {
+        self.user_textures.add_image_with_options(image, options)
+    }
+
+    /// Same as [`EguiContexts::add_image_with_options`], but registers many images at once
+    /// (see [`EguiUserTextures::add_images`]).
+    pub fn add_images(
+        &mut self,
+        images: &[Handle<Image>],
+        options: EguiUserTextureOptions,
+    ) -> Vec<BevyEguiTextureId> {
+        self.user_textures.add_images(images, options)
+    }
+
+    /// Same as [`EguiContexts::add_image_with_options`], but registers the image under
+    /// a caller-chosen id (see [`EguiUserTextures::add_image_with_id`]).
+    pub fn add_image_with_id(
+        &mut self,
+        image: Handle<Image>,
+        id: u64,
+        options: EguiUserTextureOptions,
+    ) -> Result<BevyEguiTextureId, EguiTextureIdInUse> {
+        self.user_textures.add_image_with_id(image, id, options)
     }
 
     /// Removes the image handle and an Egui texture id associated with it.
+    #[track_caller]
     pub fn remove_image(&mut self, image: &Handle<Image>) -> Option<egui::TextureId> {
-        let id = self.textures.remove(image);
-        log::debug!("Remove image (id: {:?}, handle: {:?})", id, image);
-        id.map(egui::TextureId::User)
+        self.user_textures.remove_image(image)
     }
 
     /// Returns an associated Egui texture id.
     #[must_use]
-    pub fn image_id(&self, image: &Handle<Image>) -> Option<egui::TextureId> {
-        self.textures
-            .get(image)
-            .map(|&id| egui::TextureId::User(id))
+    #[track_caller]
+    pub fn image_id(&self, image: &Handle<Image>) -> Option<BevyEguiTextureId> {
+        self.user_textures.image_id(image)
     }
-}
 
-/// Stores physical size and scale factor, is used as a helper to calculate logical size.
-#[derive(Component, Debug, Default, Clone, Copy, PartialEq, ExtractComponent)]
-pub struct WindowSize {
-    physical_width: f32,
-    physical_height: f32,
-    scale_factor: f32,
+    /// Returns the image handle associated with an Egui texture id.
+    #[must_use]
+    #[track_caller]
+    pub fn image_handle(&self, id: impl Into<egui::TextureId>) -> Option<&Handle<Image>> {
+        self.user_textures.image_handle(id)
+    }
 }
 
-impl WindowSize {
-    fn new(physical_width: f32, physical_height: f32, scale_factor: f32) -> Self {
-        Self {
-            physical_width,
-            physical_height,
-            scale_factor,
-        }
-    }
+/// Keeps running Egui frames of a window while it's unfocused, even if
+/// [`EguiSettings::render_when_unfocused`] is `false`.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct EguiRenderWhenUnfocused;
 
-    #[inline]
-    fn width(&self) -> f32 {
-        self.physical_width / self.scale_factor
+/// Overrides [`EguiSettings::feathering_size_in_pixels`] for a window.
+///
+/// Insert this component into a window entity to anti-alias its shapes differently from other
+/// windows (for example, to keep a pixel-art UI crisp with `EguiFeathering(0.0)`).
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct EguiFeathering(pub f32);
+
+/// Overrides how pointer positions of a window are mapped to Egui coordinates.
+///
+/// Insert this component into a window entity if the Egui context isn't displayed over the whole
+/// window (for example, if it's rendered to a texture shown on a curved surface). The callback
+/// receives a pointer position in physical pixels and returns a position in Egui points, or
+/// [`None`] to treat the pointer as being outside the Egui surface.
+///
+/// Without this component, a position is divided by the window scale factor and
+/// [`EguiSettings::scale_factor`].
+#[derive(Component)]
+pub struct EguiPointerTransform(Box<dyn Fn(Vec2) -> Option<egui::Pos2> + Send + Sync>);
+
+impl EguiPointerTransform {
+    /// Creates a pointer transform from a callback.
+    pub fn new(transform: impl Fn(Vec2) -> Option<egui::Pos2> + Send + Sync + 'static) -> Self {
+        Self(Box::new(transform))
     }
 
-    #[inline]
-    fn height(&self) -> f32 {
-        self.physical_height / self.scale_factor
+    /// Maps a pointer position in physical pixels to Egui points.
+    #[must_use]
+    pub fn transform(&self, physical_position: Vec2) -> Option<egui::Pos2> {
+        (self.0)(physical_position)
     }
 }
 
-/// The names of `bevy_egui` nodes.
-pub mod node {
-    /// The main egui pass.
-    pub const EGUI_PASS: &str = "egui_pass";
+/// Rewrites the input events of a window's context before they are passed to Egui.
+///
+/// Insert this component into a window entity to remap keys, expand macros or record and replay
+/// input. The callback is invoked by [`EguiSet::BeginFrame`] every frame, right before
+/// [`egui::Context::begin_frame`], even if there are no events. It receives all the events of the
+/// frame: the ones read by [`EguiSet::ProcessInput`], followed by the ones pushed into
+/// [`EguiInput`] by systems running between the two sets. The callback isn't invoked for frames
+/// skipped because of [`EguiSettings::render_when_unfocused`].
+#[derive(Component)]
+pub struct EguiInputEventsHook(Box<InputEventsHookFn>);
+
+type InputEventsHookFn = dyn FnMut(&mut Vec<egui::Event>) + Send + Sync;
+
+impl EguiInputEventsHook {
+    /// Creates an input events hook from a callback.
+    pub fn new(hook: impl FnMut(&mut Vec<egui::Event>) + Send + Sync + 'static) -> Self {
+        Self(Box::new(hook))
+    }
+
+    /// Invokes the callback with the events of a frame.
+    pub fn rewrite(&mut self, events: &mut Vec<egui::Event>) {
+        (self.0)(events);
+    }
 }
 
-#[derive(SystemSet, Clone, Hash, Debug, Eq, PartialEq)]
-/// The `bevy_egui` plugin startup system sets.
-pub enum EguiStartupSet {
-    /// Initializes Egui contexts for available windows.
-    InitContexts,
+/// Confines the rendering of a window's Egui to the opaque parts of a mask image.
+///
+/// Insert this component into a window entity to show Egui only inside an irregular area, which
+/// clip rectangles can't describe (for example, a cracked screen). The mask is stretched over the
+/// whole window, Egui fragments where its alpha is below `0.5` are discarded. Egui isn't rendered
+/// in the window until the mask image is loaded.
+///
+/// Only rendering is affected: combine the mask with an [`EguiPointerTransform`] to ignore the
+/// pointer outside of it.
+#[derive(Component, Clone, Debug)]
+#[cfg_attr(feature = "render", derive(ExtractComponent))]
+pub struct EguiMask(pub Handle<Image>);
+
+/// Is sent when Egui copies text (e.g. when the copy shortcut is pressed in a text edit).
+///
+/// The event is sent regardless of [`EguiSettings`], so apps can route copied text through
+/// their own channel, for example, with the system clipboard disabled.
+#[derive(Event, Clone, Debug, PartialEq, Eq)]
+pub struct EguiCopyRequest {
+    /// The window whose context has copied the text.
+    pub window: Entity,
+    /// The copied text ([`egui::PlatformOutput::copied_text`]).
+    pub text: String,
 }
 
-/// The `bevy_egui` plugin system sets.
-#[derive(SystemSet, Clone, Hash, Debug, Eq, PartialEq)]
-pub enum EguiSet {
-    /// Initializes Egui contexts for newly created windows.
-    InitContexts,
-    /// Reads Egui inputs (keyboard, mouse, etc) and writes them into the [`EguiInput`] resource.
-    ///
-    /// To modify the input, you can hook your system like this:
-    ///
-    /// `system.after(EguiSet::ProcessInput).before(EguiSet::BeginFrame)`.
-    ProcessInput,
-    /// Begins the `egui` frame.
-    BeginFrame,
-    /// Processes the [`EguiOutput`] resource.
-    ProcessOutput,
+/// Is sent when a widget dragged in Egui is released outside of any Egui area.
+///
+/// Drags that end over Egui are handled by Egui itself. This event lets an app handle the ones
+/// that cross the boundary, for example, to spawn an entity at the position where an item
+/// dragged from a palette was dropped. To check whether a drag is in progress, use
+/// `ctx.memory(|memory| memory.is_anything_being_dragged())`.
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct EguiDragReleased {
+    /// The window in which the drag was released.
+    pub window: Entity,
+    /// The position where the drag was released, in logical pixels of the window.
+    pub pos: Vec2,
+}
+
+/// A resource for storing `bevy_egui` mouse position.
+#[derive(Resource, Component, Default, Deref, DerefMut)]
+pub struct EguiMousePosition(pub Option<(Entity, egui::Vec2)>);
+
+/// What Egui painted and claimed in each window in its last ended frame, for gating app systems
+/// (e.g. pausing a background simulation while a full-screen menu is open).
+///
+/// Entries are updated by [`EguiSet::ProcessOutput`], so systems that run after it (or in the
+/// next frame's [`PreUpdate`] and [`Update`](bevy::app::Update)) see the state of the last
+/// frame. Windows whose frames are skipped (see [`EguiSettings::render_when_unfocused`]) keep
+/// their last entries, and closed windows are removed.
+#[derive(Resource, Clone, Debug, Default, Deref, DerefMut)]
+pub struct EguiUiActivity(pub HashMap<Entity, EguiWindowActivity>);
+
+/// What Egui painted and claimed in a window in a frame, see [`EguiUiActivity`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EguiWindowActivity {
+    /// The number of tessellated primitives ([`EguiRenderOutput::paint_jobs`]), `0` if Egui
+    /// painted nothing.
+    pub primitive_count: usize,
+    /// The bounding rectangle of the painted meshes (in points, clipped to their clip
+    /// rectangles), [`egui::Rect::NOTHING`] if Egui painted nothing.
+    pub painted_rect: egui::Rect,
+    /// The screen rectangle of the frame (in points).
+    pub screen_rect: egui::Rect,
+    /// Whether Egui wants the pointer input, see [`egui::Context::wants_pointer_input`].
+    pub wants_pointer_input: bool,
+    /// Whether Egui wants the keyboard input (e.g. a text field is focused), see
+    /// [`egui::Context::wants_keyboard_input`].
+    pub wants_keyboard_input: bool,
+}
+
+impl EguiWindowActivity {
+    /// Whether Egui painted anything in the frame.
+    #[must_use]
+    pub fn is_painting(&self) -> bool {
+        self.primitive_count > 0
+    }
+
+    /// The share of the screen covered by [`EguiWindowActivity::painted_rect`], from `0.0` to
+    /// `1.0`.
+    ///
+    /// A full-screen panel (such as an [`egui::CentralPanel`]) makes it `1.0`. As it's based on
+    /// the bounding rectangle, small widgets in opposite corners of the screen make it high as
+    /// well.
+    #[must_use]
+    pub fn painted_share(&self) -> f32 {
+        let screen_area = self.screen_rect.area();
+        if !self.is_painting() || screen_area <= 0.0 {
+            return 0.0;
+        }
+        let painted_rect = self.painted_rect.intersect(self.screen_rect);
+        if !painted_rect.is_positive() {
+            return 0.0;
+        }
+        (painted_rect.area() / screen_area).min(1.0)
+    }
+}
+
+/// Bevy-side options of a user texture, that aren't covered by Egui's texture options.
+#[derive(Clone, Debug, Default)]
+pub struct EguiUserTextureOptions {
+    /// Overrides the sampler of the image when it's displayed in Egui.
+    ///
+    /// Can be used to enable anisotropic filtering for images that are scaled down:
+    ///
+    /// ```rust
+    /// use bevy::render::texture::ImageSamplerDescriptor;
+    /// use bevy_egui::EguiUserTextureOptions;
+    ///
+    /// let options = EguiUserTextureOptions {
+    ///     // Anisotropic filtering requires all the filter modes to be linear.
+    ///     sampler: Some(ImageSamplerDescriptor {
+    ///         anisotropy_clamp: 16,
+    ///         ..ImageSamplerDescriptor::linear()
+    ///     }),
+    ///     ..Default::default()
+    /// };
+    /// ```
+    pub sampler: Option<ImageSamplerDescriptor>,
+    /// Generates mipmaps for the image, so that it doesn't alias when it's displayed scaled down
+    /// (`false` by default).
+    ///
+    /// The mipmaps are generated on the CPU from the image data into a copy of the image, which is
+    /// regenerated when the image is modified. Without an overridden
+    /// [`EguiUserTextureOptions::sampler`], the copy is sampled with trilinear filtering
+    /// ([`ImageSamplerDescriptor::linear`]). Only 2D images with a single layer and an 8-bit RGBA
+    /// or BGRA format are supported, others are displayed without mipmaps (an error is logged).
+    /// Images that already have mipmaps are used as they are. Images drawn on the GPU (camera
+    /// render targets) don't have up-to-date CPU data, so they shouldn't use this option.
+    pub generate_mipmaps: bool,
+    /// Mirrors the image horizontally by flipping the U texture coordinate.
+    pub flip_u: bool,
+    /// Mirrors the image vertically by flipping the V texture coordinate.
+    ///
+    /// Is useful for images that come from sources with the bottom-left origin.
+    pub flip_v: bool,
+    /// Maps texture channels to displayed colors, see [`EguiColormap`].
+    pub colormap: EguiColormap,
+    /// Displays a single face of a cubemap image (an image with 6 array layers).
+    ///
+    /// If the image isn't a cubemap, an error is logged and the image isn't displayed.
+    pub cubemap_face: Option<EguiCubemapFace>,
+}
+
+/// A face of a cubemap image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EguiCubemapFace {
+    /// The face looking at `+X`.
+    PositiveX,
+    /// The face looking at `-X`.
+    NegativeX,
+    /// The face looking at `+Y`.
+    PositiveY,
+    /// The face looking at `-Y`.
+    NegativeY,
+    /// The face looking at `+Z`.
+    PositiveZ,
+    /// The face looking at `-Z`.
+    NegativeZ,
+}
+
+impl EguiCubemapFace {
+    /// All the faces, in the order of the cubemap array layers.
+    pub const ALL: [Self; 6] = [
+        Self::PositiveX,
+        Self::NegativeX,
+        Self::PositiveY,
+        Self::NegativeY,
+        Self::PositiveZ,
+        Self::NegativeZ,
+    ];
+
+    /// The array layer of the face in a cubemap image.
+    #[must_use]
+    pub fn array_layer(self) -> u32 {
+        self as u32
+    }
+}
+
+/// Defines how the channels of a user texture are mapped to displayed colors.
+///
+/// Is useful for previewing data textures (heightmaps, flow fields, etc.) without converting them
+/// to RGBA.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum EguiColormap {
+    /// Displays the texture as is. Missing channels are sampled as `0.0` (alpha as `1.0`), so
+    /// single-channel textures appear red.
+    #[default]
+    Rgba,
+    /// Displays the red channel as a grayscale image.
+    Grayscale,
+    /// Maps the red channel from `0.0` to `1.0` to a blue-cyan-green-yellow-red heatmap.
+    Heatmap,
+}
+
+impl EguiUserTextureOptions {
+    /// Options with a linear sampler that is clamped to a border color outside of the image.
+    ///
+    /// Egui's [`egui::TextureOptions`] can't express this wrap mode. The render device has to
+    /// support [`WgpuFeatures::ADDRESS_MODE_CLAMP_TO_BORDER`](bevy::render::settings::WgpuFeatures::ADDRESS_MODE_CLAMP_TO_BORDER),
+    /// otherwise an error is logged and the image's own sampler is used. Only the border colors
+    /// supported by wgpu are available (arbitrary colors aren't).
+    #[must_use]
+    pub fn clamp_to_border(border_color: ImageSamplerBorderColor) -> Self {
+        Self {
+            sampler: Some(ImageSamplerDescriptor {
+                address_mode_u: ImageAddressMode::ClampToBorder,
+                address_mode_v: ImageAddressMode::ClampToBorder,
+                border_color: Some(border_color),
+                ..ImageSamplerDescriptor::linear()
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Applies [`EguiUserTextureOptions::flip_u`] and [`EguiUserTextureOptions::flip_v`].
+    #[cfg(any(feature = "render", test))]
+    pub(crate) fn map_uv(&self, uv: egui::Pos2) -> egui::Pos2 {
+        egui::pos2(
+            if self.flip_u { 1.0 - uv.x } else { uv.x },
+            if self.flip_v { 1.0 - uv.y } else { uv.y },
+        )
+    }
+
+    #[cfg(any(feature = "render", test))]
+    pub(crate) fn flips_uv(&self) -> bool {
+        self.flip_u || self.flip_v
+    }
+
+    /// The sampler that overrides the one of the image, see [`EguiUserTextureOptions::sampler`]
+    /// and [`EguiUserTextureOptions::generate_mipmaps`].
+    #[cfg(feature = "render")]
+    pub(crate) fn sampler_descriptor(&self) -> Option<ImageSamplerDescriptor> {
+        self.sampler
+            .clone()
+            .or_else(|| self.generate_mipmaps.then(ImageSamplerDescriptor::linear))
+    }
+}
+
+/// The error returned by [`EguiUserTextures::add_image_with_id`] if the requested id is already
+/// used by another image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EguiTextureIdInUse(pub u64);
+
+impl std::fmt::Display for EguiTextureIdInUse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "user texture id {} is already in use", self.0)
+    }
+}
+
+impl std::error::Error for EguiTextureIdInUse {}
+
+/// An Egui texture id issued by [`EguiUserTextures`] for a user texture.
+///
+/// Remembers the image asset it was issued for. Derefs to and converts into
+/// [`egui::TextureId`], so it can be passed to Egui widgets directly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BevyEguiTextureId {
+    texture_id: egui::TextureId,
+    asset_id: AssetId<Image>,
+}
+
+impl BevyEguiTextureId {
+    fn new(id: u64, image: &Handle<Image>) -> Self {
+        Self {
+            texture_id: egui::TextureId::User(id),
+            asset_id: image.id(),
+        }
+    }
+
+    /// The id of the user texture (as in [`egui::TextureId::User`]).
+    #[must_use]
+    pub fn id(&self) -> u64 {
+        match self.texture_id {
+            egui::TextureId::User(id) => id,
+            egui::TextureId::Managed(_) => unreachable!(),
+        }
+    }
+
+    /// The image asset the id was issued for.
+    #[must_use]
+    pub fn asset_id(&self) -> AssetId<Image> {
+        self.asset_id
+    }
+}
+
+impl Deref for BevyEguiTextureId {
+    type Target = egui::TextureId;
+
+    fn deref(&self) -> &Self::Target {
+        &self.texture_id
+    }
+}
+
+impl From<BevyEguiTextureId> for egui::TextureId {
+    fn from(texture_id: BevyEguiTextureId) -> Self {
+        texture_id.texture_id
+    }
+}
+
+impl PartialEq<egui::TextureId> for BevyEguiTextureId {
+    fn eq(&self, other: &egui::TextureId) -> bool {
+        self.texture_id == *other
+    }
+}
+
+/// A resource for storing `bevy_egui` user textures.
+///
+/// The ids are shared by all windows and aren't tied to their Egui contexts: an image keeps its
+/// id when a context is recreated (e.g. replaced with [`EguiContext::new`]) or its window is
+/// closed, until the image is removed.
+#[derive(Clone, Resource, Default)]
+#[cfg_attr(feature = "render", derive(ExtractResource))]
+pub struct EguiUserTextures {
+    textures: HashMap<Handle<Image>, u64>,
+    /// The images of `textures` by their ids, for looking the handles up and skipping the claimed
+    /// ids when assigning new ones.
+    images: HashMap<u64, Handle<Image>>,
+    options: HashMap<u64, EguiUserTextureOptions>,
+    /// Copies of the images with generated mipmaps
+    /// (see [`EguiUserTextureOptions::generate_mipmaps`]).
+    #[cfg(feature = "render")]
+    mipmapped_images: HashMap<u64, Handle<Image>>,
+    last_texture_id: u64,
+}
+
+impl EguiUserTextures {
+    /// Can accept either a strong or a weak handle.
+    ///
+    /// You may want to pass a weak handle if you control removing texture assets in your
+    /// application manually and you don't want to bother with cleaning up textures in Egui.
+    ///
+    /// You'll want to pass a strong handle if a texture is used only in Egui and there are no
+    /// handle copies stored anywhere else.
+    ///
+    /// Bind groups for user textures are recreated from the current GPU image every frame,
+    /// so images used as camera render targets are displayed live without re-registering them
+    /// (see [./examples/render_to_image_widget.rs](https://github.com/mvlabat/bevy_egui/blob/v0.20.1/examples/render_to_image_widget.rs)).
+    ///
+    /// The id is allocated synchronously and stays the same for as long as the image is
+    /// registered: adding the same handle again returns the existing id. The image doesn't need
+    /// to be loaded yet, meshes that use the id are just skipped until it is.
+    pub fn add_image(&mut self, image: Handle<Image>) -> BevyEguiTextureId {
+        if let Some(&id) = self.textures.get(&image) {
+            return BevyEguiTextureId::new(id, &image);
+        }
+        let id = self.next_texture_id();
+        log::debug!("Add a new image (id: {}, handle: {:?})", id, image);
+        let texture_id = BevyEguiTextureId::new(id, &image);
+        self.textures.insert(image.clone(), id);
+        self.images.insert(id, image);
+        texture_id
+    }
+
+    /// Claims the next id that isn't in use.
+    fn next_texture_id(&mut self) -> u64 {
+        // Skip the ids claimed with `add_image_with_id`.
+        while self.images.contains_key(&self.last_texture_id) {
+            self.last_texture_id += 1;
+        }
+        let id = self.last_texture_id;
+        self.last_texture_id += 1;
+        id
+    }
+
+    /// Same as [`EguiUserTextures::add_image`], but also sets Bevy-side options of the texture.
+    ///
+    /// If the image is already added, its options are replaced.
+    pub fn add_image_with_options(
+        &mut self,
+        image: Handle<Image>,
+        options: EguiUserTextureOptions,
+    ) -> BevyEguiTextureId {
+        let texture_id = self.add_image(image);
+        self.options.insert(texture_id.id(), options);
+        texture_id
+    }
+
+    /// Same as [`EguiUserTextures::add_image_with_options`], but registers many images at once.
+    ///
+    /// Returns the ids in the order of `images` (adding thumbnails of a whole asset folder,
+    /// for example).
+    pub fn add_images(
+        &mut self,
+        images: &[Handle<Image>],
+        options: EguiUserTextureOptions,
+    ) -> Vec<BevyEguiTextureId> {
+        images
+            .iter()
+            .map(|image| {
+                let id = match self.textures.get(image) {
+                    Some(&id) => id,
+                    None => {
+                        let id = self.next_texture_id();
+                        log::debug!("Add a new image (id: {}, handle: {:?})", id, image);
+                        self.textures.insert(image.clone(), id);
+                        self.images.insert(id, image.clone());
+                        id
+                    }
+                };
+                self.options.insert(id, options.clone());
+                BevyEguiTextureId::new(id, image)
+            })
+            .collect()
+    }
+
+    /// Same as [`EguiUserTextures::add_image_with_options`], but registers the image under
+    /// a caller-chosen id instead of an automatically assigned one.
+    ///
+    /// Is useful for interop with code that relies on specific [`egui::TextureId::User`] values
+    /// (persisted UI layouts, for example). Ids assigned by [`EguiUserTextures::add_image`] skip
+    /// the claimed ones. If the image is already added under a different id, it's moved to the
+    /// new one.
+    pub fn add_image_with_id(
+        &mut self,
+        image: Handle<Image>,
+        id: u64,
+        options: EguiUserTextureOptions,
+    ) -> Result<BevyEguiTextureId, EguiTextureIdInUse> {
+        if self
+            .images
+            .get(&id)
+            .is_some_and(|claimed| *claimed != image)
+        {
+            return Err(EguiTextureIdInUse(id));
+        }
+        log::debug!("Add a new image (id: {}, handle: {:?})", id, image);
+        let texture_id = BevyEguiTextureId::new(id, &image);
+        if let Some(previous_id) = self.textures.insert(image.clone(), id) {
+            if previous_id != id {
+                self.release_texture_id(previous_id);
+            }
+        }
+        self.images.insert(id, image);
+        self.options.insert(id, options);
+        Ok(texture_id)
+    }
+
+    /// Creates an image from raw pixels and registers it.
+    ///
+    /// `rgba` is expected to contain `width * height` non-premultiplied sRGB pixels, 4 bytes
+    /// each (as [`TextureFormat::Rgba8UnormSrgb`]).
+    /// The registered handle is strong, so the image lives for as long as it's registered: it's
+    /// freed after the handle is removed with [`EguiUserTextures::remove_image`] (the handle can be
+    /// looked up with [`EguiUserTextures::image_handle`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `rgba` doesn't match the dimensions.
+    pub fn add_rgba(
+        &mut self,
+        images: &mut Assets<Image>,
+        width: u32,
+        height: u32,
+        rgba: Vec<u8>,
+        options: EguiUserTextureOptions,
+    ) -> BevyEguiTextureId {
+        assert_eq!(
+            rgba.len(),
+            width as usize * height as usize * 4,
+            "RGBA data doesn't match the image dimensions ({width}x{height})"
+        );
+        let image = Image::new(
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            rgba,
+            TextureFormat::Rgba8UnormSrgb,
+        );
+        self.add_image_with_options(images.add(image), options)
+    }
+
+    /// Removes the image handle and an Egui texture id associated with it.
+    pub fn remove_image(&mut self, image: &Handle<Image>) -> Option<egui::TextureId> {
+        let id = self.textures.remove(image);
+        log::debug!("Remove image (id: {:?}, handle: {:?})", id, image);
+        if let Some(id) = id {
+            self.release_texture_id(id);
+        }
+        id.map(egui::TextureId::User)
+    }
+
+    /// Drops the image, the options and the mipmapped copy of an id that is no longer in use.
+    fn release_texture_id(&mut self, id: u64) {
+        self.images.remove(&id);
+        self.options.remove(&id);
+        #[cfg(feature = "render")]
+        self.mipmapped_images.remove(&id);
+    }
+
+    /// Returns Bevy-side options of a texture, if they were set.
+    #[must_use]
+    pub fn image_options(&self, image: &Handle<Image>) -> Option<&EguiUserTextureOptions> {
+        self.textures.get(image).and_then(|id| self.options.get(id))
+    }
+
+    /// Returns an associated Egui texture id.
+    #[must_use]
+    pub fn image_id(&self, image: &Handle<Image>) -> Option<BevyEguiTextureId> {
+        self.textures
+            .get(image)
+            .map(|&id| BevyEguiTextureId::new(id, image))
+    }
+
+    /// Returns the image handle associated with an Egui texture id.
+    ///
+    /// Returns `None` for managed textures (such as the font atlas), as they aren't exposed as
+    /// Bevy assets.
+    #[must_use]
+    pub fn image_handle(&self, id: impl Into<egui::TextureId>) -> Option<&Handle<Image>> {
+        let egui::TextureId::User(id) = id.into() else {
+            return None;
+        };
+        self.images.get(&id)
+    }
+}
+
+/// The font scale factor of a context, together with the unscaled and scaled sizes of each text
+/// style (to detect the styles changed after scaling).
+type FontScaleState = (f32, BTreeMap<egui::TextStyle, (f32, f32)>);
+
+fn font_scale_id() -> egui::Id {
+    egui::Id::new("bevy_egui_font_scale")
+}
+
+fn context_font_scale(ctx: &egui::Context) -> f32 {
+    ctx.data(|data| data.get_temp::<FontScaleState>(font_scale_id()))
+        .map_or(1.0, |(factor, _)| factor)
+}
+
+fn set_context_font_scale(ctx: &egui::Context, factor: f32) {
+    if !factor.is_finite() || factor <= 0.0 {
+        log::error!("Invalid font scale: {factor}");
+        return;
+    }
+    let (_, sizes) = ctx
+        .data(|data| data.get_temp::<FontScaleState>(font_scale_id()))
+        .unwrap_or_default();
+    let mut style = (*ctx.style()).clone();
+    let mut new_sizes = BTreeMap::new();
+    for (text_style, font_id) in style.text_styles.iter_mut() {
+        let unscaled_size = match sizes.get(text_style) {
+            Some(&(unscaled_size, scaled_size)) if scaled_size == font_id.size => unscaled_size,
+            // The size was changed after scaling, so it's treated as the new unscaled one.
+            _ => font_id.size,
+        };
+        font_id.size = unscaled_size * factor;
+        new_sizes.insert(text_style.clone(), (unscaled_size, font_id.size));
+    }
+    ctx.set_style(style);
+    ctx.data_mut(|data| data.insert_temp(font_scale_id(), (factor, new_sizes)));
+}
+
+fn egui_to_physical_pos(pos: egui::Pos2, pixels_per_point: f32) -> Vec2 {
+    Vec2::new(pos.x, pos.y) * pixels_per_point
+}
+
+fn physical_to_egui_pos(pos: Vec2, pixels_per_point: f32) -> egui::Pos2 {
+    let pos = pos / pixels_per_point;
+    egui::pos2(pos.x, pos.y)
+}
+
+fn egui_to_viewport_pos(
+    pos: egui::Pos2,
+    pixels_per_point: f32,
+    window_scale_factor: f32,
+    logical_viewport_min: Vec2,
+) -> Vec2 {
+    egui_to_physical_pos(pos, pixels_per_point) / window_scale_factor - logical_viewport_min
+}
+
+/// Stores physical size and scale factor, is used as a helper to calculate logical size.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct WindowSize {
+    physical_width: f32,
+    physical_height: f32,
+    scale_factor: f32,
+}
+
+impl WindowSize {
+    fn new(physical_width: f32, physical_height: f32, scale_factor: f32) -> Self {
+        Self {
+            physical_width,
+            physical_height,
+            scale_factor,
+        }
+    }
+
+    #[cfg(feature = "render")]
+    #[inline]
+    fn width(&self) -> f32 {
+        self.physical_width / self.scale_factor
+    }
+
+    #[cfg(feature = "render")]
+    #[inline]
+    fn height(&self) -> f32 {
+        self.physical_height / self.scale_factor
+    }
+}
+
+/// The names of `bevy_egui` nodes.
+pub mod node {
+    use bevy::ecs::entity::Entity;
+
+    /// The main egui pass.
+    pub const EGUI_PASS: &str = "egui_pass";
+
+    /// Returns the label of the Egui pass node of a window.
+    ///
+    /// Each window gets its own [`EguiNode`](crate::egui_node::EguiNode), which is added to the
+    /// main render graph with a single edge:
+    /// [`CAMERA_DRIVER`](bevy::render::main_graph::node::CAMERA_DRIVER) → Egui pass. That is,
+    /// Egui is drawn over the window's swap chain texture after all the cameras (including their
+    /// post-processing) have finished rendering.
+    ///
+    /// Nodes that have to run after Egui (a custom upscaler, for example) can be ordered with
+    /// `render_graph.add_node_edge(egui_pass(window), MY_NODE)`. The default edge can also be
+    /// removed with `render_graph.remove_node_edge`. Systems doing that have to run in the render
+    /// app's [`ExtractSchedule`](bevy::render::ExtractSchedule) after
+    /// [`setup_new_windows_render_system`](crate::render_systems::setup_new_windows_render_system),
+    /// as the node is added in the frame the window is created.
+    #[must_use]
+    pub fn egui_pass(window: Entity) -> String {
+        format!("egui-{}-{}", window.index(), window.generation())
+    }
+}
+
+#[derive(SystemSet, Clone, Hash, Debug, Eq, PartialEq)]
+/// The `bevy_egui` plugin startup system sets.
+pub enum EguiStartupSet {
+    /// Initializes Egui contexts for available windows.
+    InitContexts,
+}
+
+/// The `bevy_egui` plugin system sets.
+///
+/// Within a Bevy frame, they run in this order:
+///
+/// 1. [`EguiSet::InitContexts`], [`EguiSet::ProcessInput`] and [`EguiSet::BeginFrame`] in
+///    [`PreUpdate`] (after Bevy's [`InputSystem`]).
+/// 2. UI systems, normally in [`Update`](bevy::app::Update). Anything scheduled between
+///    [`EguiSet::BeginFrame`] and [`EguiSet::ProcessOutput`] draws into the current frame.
+/// 3. [`EguiSet::ProcessOutput`] in [`PostUpdate`]: the frame is ended and tessellated, and Egui
+///    textures are updated right after it.
+/// 4. Render extraction, after the main schedule has finished, which copies the output of
+///    [`EguiSet::ProcessOutput`] to the render world.
+///
+/// So the frame that is rendered is the one whose UI systems ran in the same Bevy frame. To make
+/// state changes visible to the UI in the same frame, order the systems that make them before the
+/// UI systems (e.g. `ui_system.after(update_state_system)` in [`Update`](bevy::app::Update)).
+/// Changes made after [`EguiSet::ProcessOutput`] (e.g. later in [`PostUpdate`] or in [`Last`])
+/// are seen by the UI only in the next frame. The sets can be ordered relative to other sets with
+/// [`App::configure_sets`].
+///
+/// [`Last`]: bevy::app::Last
+#[derive(SystemSet, Clone, Hash, Debug, Eq, PartialEq)]
+pub enum EguiSet {
+    /// Initializes Egui contexts for newly created windows and cleans up the state of closed ones.
+    ///
+    /// A window spawned at runtime gets its context when this set runs in the next frame's
+    /// [`PreUpdate`], so systems that may run in the same frame as a window is spawned should
+    /// use [`EguiContexts::try_ctx_for_window_mut`] instead of panicking getters.
+    InitContexts,
+    /// Reads Egui inputs (keyboard, mouse, etc) and writes them into the [`EguiInput`] resource.
+    ///
+    /// To modify the input, you can hook your system like this:
+    ///
+    /// `system.after(EguiSet::ProcessInput).before(EguiSet::BeginFrame)`.
+    ProcessInput,
+    /// Begins the `egui` frame.
+    BeginFrame,
+    /// Ends the `egui` frame and processes its output ([`EguiOutput`] and
+    /// [`EguiRenderOutput`]).
+    ProcessOutput,
 }
 
 impl Plugin for EguiPlugin {
@@ -577,18 +1882,24 @@ impl Plugin for EguiPlugin {
 
         let world = &mut app.world;
         world.init_resource::<EguiSettings>();
+        world.init_resource::<EguiClock>();
         world.init_resource::<EguiManagedTextures>();
         #[cfg(feature = "manage_clipboard")]
         world.init_resource::<EguiClipboard>();
         world.init_resource::<EguiUserTextures>();
         world.init_resource::<EguiMousePosition>();
+        world.init_resource::<EguiUiActivity>();
         world.insert_resource(TouchId::default());
-        app.add_plugins(ExtractResourcePlugin::<EguiUserTextures>::default());
-        app.add_plugins(ExtractResourcePlugin::<ExtractedEguiManagedTextures>::default());
-        app.add_plugins(ExtractResourcePlugin::<EguiSettings>::default());
-        app.add_plugins(ExtractComponentPlugin::<EguiContext>::default());
-        app.add_plugins(ExtractComponentPlugin::<WindowSize>::default());
-        app.add_plugins(ExtractComponentPlugin::<EguiRenderOutput>::default());
+        app.add_event::<EguiDragReleased>();
+        app.add_event::<EguiCopyRequest>();
+        #[cfg(feature = "render")]
+        {
+            app.add_plugins(ExtractResourcePlugin::<EguiUserTextures>::default());
+            app.add_plugins(ExtractResourcePlugin::<ExtractedEguiManagedTextures>::default());
+            app.add_plugins(ExtractResourcePlugin::<EguiSettings>::default());
+            app.add_plugins(ExtractComponentPlugin::<EguiContext>::default());
+            app.add_plugins(ExtractComponentPlugin::<EguiMask>::default());
+        }
 
         app.add_systems(
             PreStartup,
@@ -603,6 +1914,7 @@ impl Plugin for EguiPlugin {
         app.add_systems(
             PreUpdate,
             (
+                teardown_closed_windows_system,
                 setup_new_windows_system,
                 apply_deferred,
                 update_window_contexts_system,
@@ -627,37 +1939,17 @@ impl Plugin for EguiPlugin {
             PostUpdate,
             process_output_system.in_set(EguiSet::ProcessOutput),
         );
-        app.add_systems(
-            PostUpdate,
-            update_egui_textures_system.after(EguiSet::ProcessOutput),
-        );
-        app.add_systems(Last, free_egui_textures_system)
-            .add_systems(
-                Render,
-                render_systems::prepare_egui_transforms_system.in_set(RenderSet::Prepare),
-            )
-            .add_systems(
-                Render,
-                render_systems::queue_bind_groups_system.in_set(RenderSet::Queue),
-            )
-            .add_systems(
-                Render,
-                render_systems::queue_pipelines_system.in_set(RenderSet::Queue),
+        #[cfg(feature = "render")]
+        {
+            app.add_systems(
+                PostUpdate,
+                update_egui_textures_system.after(EguiSet::ProcessOutput),
             );
-
-        load_internal_asset!(app, EGUI_SHADER_HANDLE, "egui.wgsl", Shader::from_wgsl);
-    }
-
-    fn finish(&self, app: &mut App) {
-        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
-            render_app
-                .init_resource::<egui_node::EguiPipeline>()
-                .init_resource::<SpecializedRenderPipelines<EguiPipeline>>()
-                .init_resource::<EguiTransforms>()
-                .add_systems(
-                    ExtractSchedule,
-                    render_systems::setup_new_windows_render_system,
-                )
+            app.add_systems(
+                PostUpdate,
+                update_user_texture_mipmaps_system.after(EguiSet::ProcessOutput),
+            );
+            app.add_systems(Last, free_egui_textures_system)
                 .add_systems(
                     Render,
                     render_systems::prepare_egui_transforms_system.in_set(RenderSet::Prepare),
@@ -670,6 +1962,60 @@ impl Plugin for EguiPlugin {
                     Render,
                     render_systems::queue_pipelines_system.in_set(RenderSet::Queue),
                 );
+
+            load_internal_asset!(app, EGUI_SHADER_HANDLE, "egui.wgsl", Shader::from_wgsl);
+        }
+    }
+
+    #[cfg(feature = "render")]
+    fn finish(&self, app: &mut App) {
+        let render_device = app
+            .get_sub_app(RenderApp)
+            .ok()
+            .and_then(|render_app| render_app.world.get_resource::<RenderDevice>().cloned());
+        let Some(render_device) = render_device else {
+            return;
+        };
+        let capabilities = render_systems::check_render_capabilities(
+            &render_device,
+            &mut app.world.resource_mut::<EguiSettings>(),
+        );
+        app.world.insert_resource(capabilities);
+        if !capabilities.rendering_enabled {
+            return;
+        }
+
+        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .init_resource::<egui_node::EguiPipeline>()
+                .init_resource::<SpecializedRenderPipelines<EguiPipeline>>()
+                .init_resource::<EguiTransforms>()
+                .init_resource::<EguiUserTextureSamplers>()
+                .init_resource::<ExtractedEguiFrames>()
+                .add_systems(
+                    ExtractSchedule,
+                    (
+                        render_systems::extract_egui_frames_system,
+                        render_systems::setup_new_windows_render_system,
+                        render_systems::teardown_closed_windows_render_system,
+                    ),
+                )
+                .add_systems(
+                    Render,
+                    render_systems::prepare_egui_transforms_system.in_set(RenderSet::Prepare),
+                )
+                .add_systems(
+                    Render,
+                    render_systems::queue_bind_groups_system.in_set(RenderSet::Queue),
+                )
+                .add_systems(
+                    Render,
+                    render_systems::queue_mask_bind_groups_system.in_set(RenderSet::Queue),
+                )
+                .add_systems(
+                    Render,
+                    render_systems::queue_pipelines_system.in_set(RenderSet::Queue),
+                );
         }
     }
 }
@@ -692,6 +2038,10 @@ pub struct EguiContextQuery {
     pub window_size: &'static mut WindowSize,
     /// [`Window`] component.
     pub window: &'static mut Window,
+    /// Custom mapping of pointer positions, if a window has one.
+    pub pointer_transform: Option<&'static EguiPointerTransform>,
+    /// Feathering override, if a window has one.
+    pub feathering: Option<&'static EguiFeathering>,
 }
 
 /// Contains textures allocated and painted by Egui.
@@ -707,13 +2057,19 @@ pub struct EguiManagedTexture {
 }
 
 /// Adds bevy_egui components to newly created windows.
+///
+/// If a window already has an [`EguiContext`], it's kept, which allows adopting a context created
+/// elsewhere (see [`EguiContext::new`]).
 pub fn setup_new_windows_system(
     mut commands: Commands,
-    new_windows: Query<Entity, (Added<Window>, Without<EguiContext>)>,
+    new_windows: Query<(Entity, Has<EguiContext>), Added<Window>>,
 ) {
-    for window in new_windows.iter() {
-        commands.entity(window).insert((
-            EguiContext::default(),
+    for (window, has_context) in new_windows.iter() {
+        let mut window = commands.entity(window);
+        if !has_context {
+            window.insert(EguiContext::default());
+        }
+        window.insert((
             EguiMousePosition::default(),
             EguiRenderOutput::default(),
             EguiInput::default(),
@@ -723,7 +2079,40 @@ pub fn setup_new_windows_system(
     }
 }
 
+/// Resets `bevy_egui` state that still references closed windows.
+///
+/// Components of a closed window are removed together with its entity, and its render graph node
+/// is removed by [`render_systems::teardown_closed_windows_render_system`]. Textures managed by
+/// the window's Egui context are freed. User textures are shared between all contexts and are
+/// kept, as well as their images.
+pub fn teardown_closed_windows_system(
+    mut closed_windows: EventReader<WindowClosed>,
+    mut egui_mouse_position: ResMut<EguiMousePosition>,
+    mut egui_ui_activity: ResMut<EguiUiActivity>,
+    #[cfg(feature = "render")] mut egui_managed_textures: ResMut<EguiManagedTextures>,
+    #[cfg(feature = "render")] mut image_assets: ResMut<Assets<Image>>,
+) {
+    for event in closed_windows.read() {
+        egui_ui_activity.remove(&event.window);
+
+        if matches!(*egui_mouse_position, EguiMousePosition(Some((window, _))) if window == event.window)
+        {
+            egui_mouse_position.0 = None;
+        }
+
+        #[cfg(feature = "render")]
+        egui_managed_textures.retain(|&(window_id, _), managed_texture| {
+            if window_id != event.window {
+                return true;
+            }
+            image_assets.remove(&managed_texture.handle);
+            false
+        });
+    }
+}
+
 /// Updates textures painted by Egui.
+#[cfg(feature = "render")]
 pub fn update_egui_textures_system(
     mut egui_render_output: Query<(Entity, &mut EguiRenderOutput), With<Window>>,
     mut egui_managed_textures: ResMut<EguiManagedTextures>,
@@ -783,6 +2172,80 @@ pub fn update_egui_textures_system(
     }
 }
 
+/// Generates the mipmaps of user textures (see [`EguiUserTextureOptions::generate_mipmaps`]).
+#[cfg(feature = "render")]
+pub fn update_user_texture_mipmaps_system(
+    mut egui_user_textures: ResMut<EguiUserTextures>,
+    mut image_assets: ResMut<Assets<Image>>,
+    mut image_events: EventReader<AssetEvent<Image>>,
+    mut reported_unsupported_images: Local<HashSet<u64>>,
+) {
+    let modified_images: HashSet<AssetId<Image>> = image_events
+        .read()
+        .filter_map(|image_event| match image_event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    let user_textures = egui_user_textures.bypass_change_detection();
+    let EguiUserTextures {
+        textures,
+        images,
+        options,
+        mipmapped_images,
+        ..
+    } = user_textures;
+    let generates_mipmaps = |id: &u64| {
+        options
+            .get(id)
+            .is_some_and(|options| options.generate_mipmaps)
+    };
+    let mut changed = false;
+    mipmapped_images.retain(|id, _| {
+        let is_used = generates_mipmaps(id) && images.contains_key(id);
+        changed |= !is_used;
+        is_used
+    });
+    reported_unsupported_images.retain(generates_mipmaps);
+
+    for (handle, &id) in textures.iter() {
+        if !generates_mipmaps(&id)
+            || mipmapped_images.contains_key(&id) && !modified_images.contains(&handle.id())
+        {
+            continue;
+        }
+        let Some(image) = image_assets.get(handle) else {
+            continue;
+        };
+        if image.texture_descriptor.mip_level_count > 1 {
+            continue;
+        }
+        let Some(mipmapped_image) = egui_node::generate_mipmaps(image) else {
+            if reported_unsupported_images.insert(id) {
+                log::error!(
+                    "Can't generate mipmaps for user texture {}: only 2D images with a single layer and an 8-bit RGBA or BGRA format are supported (format: {:?})",
+                    id,
+                    image.texture_descriptor.format
+                );
+            }
+            continue;
+        };
+        match mipmapped_images.get(&id) {
+            Some(mipmapped_handle) => image_assets.insert(mipmapped_handle, mipmapped_image),
+            None => {
+                mipmapped_images.insert(id, image_assets.add(mipmapped_image));
+            }
+        }
+        changed = true;
+    }
+
+    if changed {
+        egui_user_textures.set_changed();
+    }
+}
+
+#[cfg(feature = "render")]
 fn free_egui_textures_system(
     mut egui_user_textures: ResMut<EguiUserTextures>,
     mut egui_render_output: Query<(Entity, &mut EguiRenderOutput), With<Window>>,
@@ -832,23 +2295,1523 @@ mod tests {
         version_sync::assert_markdown_deps_updated!("README.md");
     }
 
+    fn headless_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(
+            DefaultPlugins
+                .set(RenderPlugin {
+                    render_creation: bevy::render::settings::RenderCreation::Automatic(
+                        WgpuSettings {
+                            backends: None,
+                            ..Default::default()
+                        },
+                    ),
+                })
+                .build()
+                .disable::<WinitPlugin>(),
+        )
+        .add_plugins(EguiPlugin);
+        app
+    }
+
+    /// Returns a headless app that has run its first update (so the Egui context of the primary
+    /// window is created), and the primary window.
+    fn headless_app_with_window() -> (App, Entity) {
+        let mut app = headless_app();
+        app.update();
+        let window = app
+            .world
+            .query_filtered::<Entity, With<PrimaryWindow>>()
+            .single(&app.world);
+        (app, window)
+    }
+
     #[test]
     fn test_headless_mode() {
-        App::new()
-            .add_plugins(
-                DefaultPlugins
-                    .set(RenderPlugin {
-                        render_creation: bevy::render::settings::RenderCreation::Automatic(
-                            WgpuSettings {
-                                backends: None,
-                                ..Default::default()
-                            },
-                        ),
+        headless_app().update();
+    }
+
+    #[test]
+    fn test_ui_activity() {
+        #[derive(Resource, Default)]
+        struct ShowMenu(bool);
+
+        let (mut app, window) = headless_app_with_window();
+        app.init_resource::<ShowMenu>().add_systems(
+            bevy::app::Update,
+            |mut contexts: EguiContexts, show_menu: bevy::prelude::Res<ShowMenu>| {
+                if show_menu.0 {
+                    egui::CentralPanel::default().show(contexts.ctx_mut(), |ui| {
+                        ui.label("Paused");
+                    });
+                }
+            },
+        );
+        app.update();
+
+        let activity = app.world.resource::<EguiUiActivity>()[&window];
+        assert!(!activity.is_painting());
+        assert_eq!(activity.painted_share(), 0.0);
+        assert!(!activity.wants_pointer_input && !activity.wants_keyboard_input);
+
+        app.world.resource_mut::<ShowMenu>().0 = true;
+        app.update();
+        let activity = app.world.resource::<EguiUiActivity>()[&window];
+        assert!(activity.is_painting());
+        assert!(activity.painted_share() > 0.99);
+
+        app.world.resource_mut::<ShowMenu>().0 = false;
+        app.world.send_event(bevy::window::WindowClosed { window });
+        app.world.despawn(window);
+        app.update();
+        assert!(app.world.resource::<EguiUiActivity>().is_empty());
+    }
+
+    #[test]
+    fn test_fixed_clock() {
+        let mut app = headless_app();
+        app.insert_resource(EguiClock::Fixed(0.25));
+
+        for _ in 0..4 {
+            app.update();
+        }
+
+        let mut contexts = app.world.query::<&mut EguiContext>();
+        let mut ctx = contexts.single_mut(&mut app.world);
+        assert_eq!(ctx.get_mut().input(|input| input.time), 1.0);
+    }
+
+    #[test]
+    fn test_coordinate_conversions() {
+        for (window_scale_factor, egui_scale_factor) in [(1.25, 1.0), (1.5, 0.8), (1.75, 1.5)] {
+            let pixels_per_point = window_scale_factor * egui_scale_factor;
+            let pos = egui::pos2(100.0, 37.5);
+
+            let physical = egui_to_physical_pos(pos, pixels_per_point);
+            assert_eq!(physical, Vec2::new(100.0, 37.5) * pixels_per_point);
+            let back = physical_to_egui_pos(physical, pixels_per_point);
+            assert!((back - pos).length() < 1e-4, "{back:?} != {pos:?}");
+
+            // Egui points scaled by the Egui scale factor are logical pixels.
+            let viewport_pos =
+                egui_to_viewport_pos(pos, pixels_per_point, window_scale_factor, Vec2::ZERO);
+            assert!((viewport_pos - Vec2::new(100.0, 37.5) * egui_scale_factor).length() < 1e-4);
+            let offset_pos = egui_to_viewport_pos(
+                pos,
+                pixels_per_point,
+                window_scale_factor,
+                Vec2::new(10.0, 20.0),
+            );
+            assert!((viewport_pos - offset_pos - Vec2::new(10.0, 20.0)).length() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_coordinate_conversions_in_ui_systems() {
+        #[derive(Resource, Default)]
+        struct Converted(Option<(Vec2, egui::Pos2)>);
+
+        let (mut app, window) = headless_app_with_window();
+        app.init_resource::<Converted>().add_systems(
+            bevy::app::Update,
+            |contexts: EguiContexts,
+             windows: Query<Entity, With<PrimaryWindow>>,
+             mut converted: bevy::prelude::ResMut<Converted>| {
+                let window = windows.single();
+                converted.0 = contexts
+                    .egui_to_physical(window, egui::pos2(10.0, 20.0))
+                    .zip(contexts.physical_to_egui(window, Vec2::new(30.0, 60.0)));
+            },
+        );
+        app.world
+            .get_mut::<Window>(window)
+            .unwrap()
+            .resolution
+            .set_scale_factor_override(Some(2.0));
+        app.world.resource_mut::<EguiSettings>().scale_factor = 1.5;
+
+        app.update();
+        assert_eq!(
+            app.world.resource::<Converted>().0,
+            Some((Vec2::new(30.0, 60.0), egui::pos2(10.0, 20.0)))
+        );
+    }
+
+    #[test]
+    fn test_user_texture_uv_flip() {
+        let top_left = egui::pos2(0.0, 0.0);
+        let bottom_right = egui::pos2(1.0, 1.0);
+        let point = egui::pos2(0.25, 0.75);
+
+        let options = EguiUserTextureOptions::default();
+        assert!(!options.flips_uv());
+        assert_eq!(options.map_uv(point), point);
+
+        let options = EguiUserTextureOptions {
+            flip_v: true,
+            ..Default::default()
+        };
+        assert_eq!(options.map_uv(top_left), egui::pos2(0.0, 1.0));
+        assert_eq!(options.map_uv(point), egui::pos2(0.25, 0.25));
+
+        let options = EguiUserTextureOptions {
+            flip_u: true,
+            flip_v: true,
+            ..Default::default()
+        };
+        assert_eq!(options.map_uv(top_left), bottom_right);
+        assert_eq!(options.map_uv(point), egui::pos2(0.75, 0.25));
+    }
+
+    #[cfg(feature = "render")]
+    #[test]
+    fn test_egui_transform() {
+        use crate::render_systems::EguiTransform;
+        use bevy::math::{Vec2, Vec4};
+
+        let tint = Vec4::new(0.5, 0.25, 1.0, 0.75);
+        // An 800x600 window (in logical pixels) is 400x300 points at Egui's scale factor of 2.
+        let transform = EguiTransform::new(WindowSize::new(1600.0, 1200.0, 2.0), 2.0, tint);
+        assert_eq!(transform.scale, Vec2::new(2.0 / 400.0, -2.0 / 300.0));
+        assert_eq!(transform.translation, Vec2::new(-1.0, 1.0));
+        assert_eq!(transform.tint, tint);
+        assert_eq!(
+            transform.uniform(),
+            [Vec4::new(2.0 / 400.0, -2.0 / 300.0, -1.0, 1.0), tint]
+        );
+
+        let transform = EguiTransform::from_window_size(WindowSize::new(1600.0, 1200.0, 2.0), 2.0);
+        assert_eq!(transform.tint, Vec4::ONE);
+    }
+
+    #[cfg(feature = "render")]
+    #[test]
+    fn test_cubemap_face_view() {
+        use crate::render_systems::cubemap_face_view_descriptor;
+        use bevy::render::render_resource::TextureViewDimension;
+
+        for (layer, face) in EguiCubemapFace::ALL.into_iter().enumerate() {
+            let descriptor = cubemap_face_view_descriptor(face, 6).unwrap();
+            assert_eq!(descriptor.dimension, Some(TextureViewDimension::D2));
+            assert_eq!(descriptor.base_array_layer, layer as u32);
+            assert_eq!(descriptor.array_layer_count, Some(1));
+        }
+
+        // Images that aren't cubemaps aren't displayed.
+        for array_layers in [1, 2, 12] {
+            assert!(
+                cubemap_face_view_descriptor(EguiCubemapFace::PositiveX, array_layers).is_none()
+            );
+        }
+    }
+
+    #[test]
+    fn test_ui_panic_recovery() {
+        fn panicking_ui_system(
+            mut contexts: EguiContexts,
+            windows: Query<Entity, With<PrimaryWindow>>,
+            mut frame: bevy::ecs::system::Local<u32>,
+        ) {
+            *frame += 1;
+            let panic_now = *frame == 2;
+            let succeeded = contexts.run_catching_panics(windows.single(), |ctx| {
+                egui::Window::new("Test").show(ctx, |ui| {
+                    if panic_now {
+                        panic!("UI bug");
+                    }
+                    ui.label("Hello");
+                });
+            });
+            assert_eq!(succeeded, !panic_now);
+        }
+
+        let mut app = headless_app();
+        app.add_systems(bevy::app::Update, panicking_ui_system);
+        for _ in 0..4 {
+            app.update();
+        }
+
+        let mut contexts = app.world.query::<&EguiContext>();
+        assert!(!contexts.single(&app.world).frame_in_progress);
+    }
+
+    #[test]
+    fn test_texture_id_at() {
+        let rect_mesh = |rect: egui::Rect, texture_id: egui::TextureId| {
+            let mut mesh = egui::Mesh::with_texture(texture_id);
+            mesh.add_rect_with_uv(
+                rect,
+                egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                egui::Color32::WHITE,
+            );
+            egui::ClippedPrimitive {
+                clip_rect: egui::Rect::EVERYTHING,
+                primitive: egui::epaint::Primitive::Mesh(mesh),
+            }
+        };
+        let from_min_size = |x: f32, y: f32, size: f32| {
+            egui::Rect::from_min_size(egui::pos2(x, y), egui::vec2(size, size))
+        };
+        let render_output = EguiRenderOutput {
+            paint_jobs: vec![
+                rect_mesh(from_min_size(0.0, 0.0, 100.0), egui::TextureId::Managed(0)),
+                rect_mesh(from_min_size(10.0, 10.0, 20.0), egui::TextureId::User(1)),
+                rect_mesh(from_min_size(50.0, 50.0, 20.0), egui::TextureId::User(2)),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            render_output.texture_id_at(egui::pos2(5.0, 5.0)),
+            Some(egui::TextureId::Managed(0))
+        );
+        assert_eq!(
+            render_output.texture_id_at(egui::pos2(25.0, 12.0)),
+            Some(egui::TextureId::User(1))
+        );
+        assert_eq!(
+            render_output.texture_id_at(egui::pos2(51.0, 69.0)),
+            Some(egui::TextureId::User(2))
+        );
+        assert_eq!(render_output.texture_id_at(egui::pos2(150.0, 5.0)), None);
+    }
+
+    #[test]
+    fn test_font_scale() {
+        let ctx = egui::Context::default();
+        let body_size = || ctx.style().text_styles[&egui::TextStyle::Body].size;
+        let default_size = body_size();
+
+        set_context_font_scale(&ctx, 2.0);
+        assert_eq!(body_size(), default_size * 2.0);
+        set_context_font_scale(&ctx, 1.5);
+        assert_eq!(body_size(), default_size * 1.5);
+        assert_eq!(context_font_scale(&ctx), 1.5);
+
+        // A style replaced after scaling is treated as unscaled.
+        let mut style = (*ctx.style()).clone();
+        style
+            .text_styles
+            .get_mut(&egui::TextStyle::Body)
+            .unwrap()
+            .size = 10.0;
+        ctx.set_style(style);
+        set_context_font_scale(&ctx, 1.5);
+        assert_eq!(body_size(), 15.0);
+        set_context_font_scale(&ctx, 1.0);
+        assert_eq!(body_size(), 10.0);
+    }
+
+    #[test]
+    fn test_add_image_with_id() {
+        let image = |id: u128| Handle::<Image>::weak_from_u128(id);
+        let mut user_textures = EguiUserTextures::default();
+
+        assert_eq!(
+            user_textures
+                .add_image_with_id(image(1), 1, Default::default())
+                .map(egui::TextureId::from),
+            Ok(egui::TextureId::User(1))
+        );
+        assert_eq!(user_textures.add_image(image(2)), egui::TextureId::User(0));
+        // The claimed id is skipped.
+        assert_eq!(user_textures.add_image(image(3)), egui::TextureId::User(2));
+        assert_eq!(
+            user_textures.add_image_with_id(image(4), 2, Default::default()),
+            Err(EguiTextureIdInUse(2))
+        );
+        assert_eq!(user_textures.image_id(&image(4)), None);
+
+        // Re-registering an image moves it to the new id.
+        assert_eq!(
+            user_textures
+                .add_image_with_id(image(1), 10, Default::default())
+                .map(egui::TextureId::from),
+            Ok(egui::TextureId::User(10))
+        );
+        assert_eq!(user_textures.image_handle(egui::TextureId::User(1)), None);
+        assert_eq!(
+            user_textures.image_handle(egui::TextureId::User(10)),
+            Some(&image(1))
+        );
+    }
+
+    #[test]
+    fn test_add_images() {
+        let image = |id: u128| Handle::<Image>::weak_from_u128(id);
+        let mut user_textures = EguiUserTextures::default();
+        user_textures
+            .add_image_with_id(image(1), 1, Default::default())
+            .unwrap();
+
+        assert_eq!(
+            user_textures.add_images(&[image(2), image(1), image(3)], Default::default()),
+            vec![
+                egui::TextureId::User(0),
+                egui::TextureId::User(1),
+                egui::TextureId::User(2)
+            ]
+        );
+        assert_eq!(user_textures.add_image(image(4)), egui::TextureId::User(3));
+    }
+
+    #[cfg(feature = "manage_clipboard")]
+    #[test]
+    fn test_pasted_text_normalization() {
+        use crate::systems::normalize_pasted_text;
+
+        assert_eq!(normalize_pasted_text("a\r\nb\rc\n", false), "a\nb\nc\n");
+        assert_eq!(
+            normalize_pasted_text("a\u{7}\tb\u{1b}\r\n", false),
+            "a\u{7}\tb\u{1b}\n"
+        );
+        assert_eq!(normalize_pasted_text("a\u{7}\tb\u{1b}\r\n", true), "a\tb\n");
+    }
+
+    #[test]
+    fn test_add_rgba() {
+        let mut images = Assets::<Image>::default();
+        let mut user_textures = EguiUserTextures::default();
+
+        let id = user_textures.add_rgba(
+            &mut images,
+            2,
+            1,
+            vec![255, 0, 0, 255, 0, 0, 255, 255],
+            Default::default(),
+        );
+        let handle = user_textures.image_handle(id).unwrap().clone();
+        assert!(handle.is_strong());
+        assert_eq!(id.asset_id(), handle.id());
+        let image = images.get(&handle).unwrap();
+        assert_eq!(image.texture_descriptor.size.width, 2);
+        assert_eq!(image.texture_descriptor.size.height, 1);
+
+        assert_eq!(user_textures.remove_image(&handle), Some(*id));
+        assert_eq!(user_textures.image_handle(id), None);
+    }
+
+    #[test]
+    fn test_scale_factor_change() {
+        let (mut app, window) = headless_app_with_window();
+        app.world.resource_mut::<EguiMousePosition>().0 = Some((window, egui::vec2(100.0, 50.0)));
+
+        fn update_and_get_scale(app: &mut App) -> (f32, f32) {
+            app.update();
+            let mut query = app.world.query::<(&Window, &EguiContext)>();
+            let (window, ctx) = query.single(&app.world);
+            (window.scale_factor() as f32, ctx.ctx.pixels_per_point())
+        }
+        assert_eq!(update_and_get_scale(&mut app), (1.0, 1.0));
+
+        app.world
+            .get_mut::<Window>(window)
+            .unwrap()
+            .resolution
+            .set_scale_factor_override(Some(2.0));
+        // The new scale factor is applied in the very next frame.
+        assert_eq!(update_and_get_scale(&mut app), (2.0, 2.0));
+        assert_eq!(
+            app.world.resource::<EguiMousePosition>().0,
+            Some((window, egui::vec2(50.0, 25.0)))
+        );
+    }
+
+    #[cfg(feature = "manage_clipboard")]
+    #[test]
+    fn test_clipboard_policy() {
+        use bevy::{
+            ecs::event::ManualEventReader,
+            input::{keyboard::KeyboardInput, ButtonState},
+            prelude::{Events, Input, KeyCode},
+            window::WindowFocused,
+        };
+
+        let (mut app, window) = headless_app_with_window();
+        app.world
+            .resource_mut::<EguiSettings>()
+            .use_system_clipboard = false;
+        app.world
+            .resource_mut::<EguiClipboard>()
+            .set_paste_contents(Some("pasted".to_owned()));
+        app.add_systems(bevy::app::Update, |mut contexts: EguiContexts| {
+            contexts
+                .ctx_mut()
+                .output_mut(|output| output.copied_text = "copied".to_owned());
+        });
+        app.update();
+
+        let mut copy_requests = ManualEventReader::<EguiCopyRequest>::default();
+        let events = app.world.resource::<Events<EguiCopyRequest>>();
+        assert_eq!(
+            copy_requests.read(events).cloned().collect::<Vec<_>>(),
+            vec![EguiCopyRequest {
+                window,
+                text: "copied".to_owned()
+            }]
+        );
+
+        app.world.send_event(WindowFocused {
+            window,
+            focused: true,
+        });
+        app.update();
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::ControlLeft);
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(KeyCode::V),
+            state: ButtonState::Pressed,
+            window,
+        });
+        app.update();
+        let mut query = app.world.query::<&mut EguiContext>();
+        let events = query
+            .single_mut(&mut app.world)
+            .get_mut()
+            .input(|input| input.events.clone());
+        assert!(events.contains(&egui::Event::Text("pasted".to_owned())));
+    }
+
+    #[test]
+    fn test_feathering_settings() {
+        let (mut app, window) = headless_app_with_window();
+
+        fn feathering(app: &mut App) -> (bool, f32) {
+            let mut query = app.world.query::<&mut EguiContext>();
+            let mut ctx = query.single_mut(&mut app.world);
+            ctx.get_mut().tessellation_options(|options| {
+                (options.feathering, options.feathering_size_in_pixels)
+            })
+        }
+        assert_eq!(feathering(&mut app), (true, 1.0));
+
+        app.world
+            .resource_mut::<EguiSettings>()
+            .feathering_size_in_pixels = 2.5;
+        app.update();
+        assert_eq!(feathering(&mut app), (true, 2.5));
+
+        app.world
+            .resource_mut::<EguiSettings>()
+            .feathering_size_in_pixels = 0.0;
+        app.update();
+        assert!(!feathering(&mut app).0);
+
+        app.world.entity_mut(window).insert(EguiFeathering(1.5));
+        app.update();
+        assert_eq!(feathering(&mut app), (true, 1.5));
+    }
+
+    #[test]
+    fn test_render_when_unfocused() {
+        let (mut app, window) = headless_app_with_window();
+        app.world
+            .resource_mut::<EguiSettings>()
+            .render_when_unfocused = false;
+        app.add_systems(bevy::app::Update, |mut contexts: EguiContexts| {
+            egui::Window::new("Test").show(contexts.ctx_mut(), |ui| ui.label("Test"));
+        });
+        // Egui doesn't paint windows in the frame they appear in.
+        app.update();
+        app.update();
+
+        fn frame_state(app: &mut App, window: Entity) -> (bool, usize) {
+            let mut query = app.world.query::<(&EguiContext, &EguiRenderOutput)>();
+            let (ctx, render_output) = query.get(&app.world, window).unwrap();
+            (ctx.is_frame_skipped(), render_output.paint_jobs.len())
+        }
+        let (frame_skipped, paint_job_count) = frame_state(&mut app, window);
+        assert!(!frame_skipped);
+        assert!(paint_job_count > 0);
+
+        app.world.get_mut::<Window>(window).unwrap().focused = false;
+        app.update();
+        // The output of the last frame is kept.
+        assert_eq!(frame_state(&mut app, window), (true, paint_job_count));
+
+        app.world.entity_mut(window).insert(EguiRenderWhenUnfocused);
+        app.update();
+        assert_eq!(frame_state(&mut app, window), (false, paint_job_count));
+    }
+
+    #[test]
+    fn test_run_outside_managed_frame() {
+        let (mut app, window) = headless_app_with_window();
+        app.world
+            .resource_mut::<EguiSettings>()
+            .render_when_unfocused = false;
+        app.update();
+
+        fn run(app: &mut App, window: Entity) -> bool {
+            let mut state = bevy::ecs::system::SystemState::<EguiContexts>::new(&mut app.world);
+            let mut has_run = false;
+            state.get_mut(&mut app.world).run(window, |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| ui.label("Test"));
+                has_run = true;
+            });
+            has_run
+        }
+        fn paint_job_count(app: &mut App) -> usize {
+            let mut query = app.world.query::<&EguiRenderOutput>();
+            query.single(&app.world).paint_jobs.len()
+        }
+        // The managed frame of the tick has already run.
+        assert!(!run(&mut app, window));
+        assert_eq!(paint_job_count(&mut app), 0);
+
+        app.world.get_mut::<Window>(window).unwrap().focused = false;
+        app.update();
+        assert!(run(&mut app, window));
+        let mut query = app.world.query::<&EguiContext>();
+        assert!(!query.single(&app.world).is_frame_skipped());
+        let count = paint_job_count(&mut app);
+        assert!(count > 0);
+        // Only one frame is run per tick.
+        assert!(!run(&mut app, window));
+        assert_eq!(paint_job_count(&mut app), count);
+    }
+
+    #[test]
+    fn test_input_grab() {
+        use bevy::{
+            input::{
+                mouse::{MouseButton, MouseButtonInput},
+                ButtonState,
+            },
+            window::CursorMoved,
+        };
+
+        let (mut app, window) = headless_app_with_window();
+        // Events pushed by app systems after `EguiSet::ProcessInput` are withheld as well.
+        app.add_systems(
+            PreUpdate,
+            (|mut egui_inputs: Query<&mut EguiInput>| {
+                for mut egui_input in egui_inputs.iter_mut() {
+                    egui_input
+                        .events
+                        .push(egui::Event::Text("injected".to_owned()));
+                }
+            })
+            .after(EguiSet::ProcessInput)
+            .before(EguiSet::BeginFrame),
+        );
+        app.update();
+        app.world.send_event(CursorMoved {
+            window,
+            position: Vec2::new(10.0, 10.0),
+        });
+        app.world.send_event(MouseButtonInput {
+            button: MouseButton::Left,
+            state: ButtonState::Pressed,
+            window,
+        });
+        app.update();
+
+        fn pointer_state(app: &mut App) -> (bool, Option<egui::Pos2>) {
+            let mut query = app.world.query::<&mut EguiContext>();
+            let mut ctx = query.single_mut(&mut app.world);
+            ctx.get_mut()
+                .input(|input| (input.pointer.any_down(), input.pointer.hover_pos()))
+        }
+        fn has_injected_event(app: &mut App) -> bool {
+            let mut query = app.world.query::<&mut EguiContext>();
+            let mut ctx = query.single_mut(&mut app.world);
+            ctx.get_mut().input(|input| {
+                input
+                    .events
+                    .contains(&egui::Event::Text("injected".to_owned()))
+            })
+        }
+        assert_eq!(
+            pointer_state(&mut app),
+            (true, Some(egui::pos2(10.0, 10.0)))
+        );
+        assert!(has_injected_event(&mut app));
+
+        let mut query = app.world.query::<&mut EguiContext>();
+        query.single_mut(&mut app.world).input_grabbed = true;
+        app.world.send_event(CursorMoved {
+            window,
+            position: Vec2::new(20.0, 20.0),
+        });
+        app.update();
+        assert_eq!(pointer_state(&mut app), (false, None));
+        assert!(!has_injected_event(&mut app));
+
+        query.single_mut(&mut app.world).input_grabbed = false;
+        app.world.send_event(CursorMoved {
+            window,
+            position: Vec2::new(30.0, 30.0),
+        });
+        app.update();
+        assert_eq!(
+            pointer_state(&mut app),
+            (false, Some(egui::pos2(30.0, 30.0)))
+        );
+    }
+
+    #[test]
+    fn test_input_events_hook() {
+        use bevy::window::CursorMoved;
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let (mut app, window) = headless_app_with_window();
+        let invocations = Arc::new(AtomicUsize::new(0));
+        let hook_invocations = invocations.clone();
+        app.world
+            .entity_mut(window)
+            .insert(EguiInputEventsHook::new(move |events| {
+                hook_invocations.fetch_add(1, Ordering::Relaxed);
+                for event in events.iter_mut() {
+                    if let egui::Event::PointerMoved(pos) = event {
+                        *pos += egui::vec2(5.0, 5.0);
+                    }
+                }
+                events.push(egui::Event::Text("x".to_owned()));
+            }));
+
+        app.world.send_event(CursorMoved {
+            window,
+            position: Vec2::new(10.0, 10.0),
+        });
+        app.update();
+        let mut query = app.world.query::<&mut EguiContext>();
+        let (hover_pos, events) = query
+            .single_mut(&mut app.world)
+            .get_mut()
+            .input(|input| (input.pointer.hover_pos(), input.events.clone()));
+        assert_eq!(hover_pos, Some(egui::pos2(15.0, 15.0)));
+        assert!(events.contains(&egui::Event::Text("x".to_owned())));
+
+        app.update();
+        assert_eq!(invocations.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_key_repeat() {
+        use bevy::{
+            input::{
+                keyboard::{KeyCode, KeyboardInput},
+                ButtonState,
+            },
+            time::TimeUpdateStrategy,
+            window::WindowFocused,
+        };
+
+        let (mut app, window) = headless_app_with_window();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(
+            std::time::Duration::from_millis(125),
+        ));
+        let mut egui_settings = app.world.resource_mut::<EguiSettings>();
+        egui_settings.key_repeat_delay = 0.25;
+        egui_settings.key_repeat_interval = 0.25;
+        app.update();
+        app.world.send_event(WindowFocused {
+            window,
+            focused: true,
+        });
+
+        fn press_a(app: &mut App, window: Entity) {
+            app.world.send_event(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(KeyCode::A),
+                state: ButtonState::Pressed,
+                window,
+            });
+        }
+        // Updates the app and returns the number of repeated key events of each frame.
+        fn repeat_counts(app: &mut App, frames: usize) -> Vec<usize> {
+            (0..frames)
+                .map(|_| {
+                    app.update();
+                    let mut query = app.world.query::<&mut EguiContext>();
+                    let mut ctx = query.single_mut(&mut app.world);
+                    ctx.get_mut().input(|input| {
+                        input
+                            .events
+                            .iter()
+                            .filter(|event| matches!(event, egui::Event::Key { repeat: true, .. }))
+                            .count()
                     })
-                    .build()
-                    .disable::<WinitPlugin>(),
+                })
+                .collect()
+        }
+
+        press_a(&mut app, window);
+        // The first repeat is sent after the delay, the next ones once per interval.
+        assert_eq!(repeat_counts(&mut app, 5), [0, 0, 1, 0, 1]);
+
+        // Once the OS repeats the key itself, no repeats are synthesized.
+        press_a(&mut app, window);
+        assert_eq!(repeat_counts(&mut app, 4), [1, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_zoom_events() {
+        use bevy::{
+            input::{
+                keyboard::KeyCode,
+                mouse::{MouseScrollUnit, MouseWheel},
+                touchpad::TouchpadMagnify,
+                Input,
+            },
+            window::CursorMoved,
+        };
+
+        let (mut app, window) = headless_app_with_window();
+        app.world.send_event(CursorMoved {
+            window,
+            position: Vec2::new(10.0, 10.0),
+        });
+        app.update();
+
+        fn zoom_and_scroll_events(app: &mut App) -> Vec<egui::Event> {
+            app.update();
+            let mut query = app.world.query::<&mut EguiContext>();
+            let mut ctx = query.single_mut(&mut app.world);
+            ctx.get_mut().input(|input| {
+                input
+                    .events
+                    .iter()
+                    .filter(|event| matches!(event, egui::Event::Zoom(_) | egui::Event::Scroll(_)))
+                    .cloned()
+                    .collect()
+            })
+        }
+        let scroll = |app: &mut App| {
+            app.world.send_event(MouseWheel {
+                unit: MouseScrollUnit::Pixel,
+                x: 0.0,
+                y: 100.0,
+                window,
+            });
+        };
+
+        app.world.send_event(TouchpadMagnify(0.5));
+        assert_eq!(
+            zoom_and_scroll_events(&mut app),
+            [egui::Event::Zoom(0.5f32.exp())]
+        );
+
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::ControlLeft);
+        scroll(&mut app);
+        assert_eq!(
+            zoom_and_scroll_events(&mut app),
+            [egui::Event::Zoom(0.5f32.exp())]
+        );
+
+        // Scrolling with Ctrl held is sent as is if the setting is disabled, pinch gestures are
+        // still sent as zooming.
+        app.world
+            .resource_mut::<EguiSettings>()
+            .zoom_with_ctrl_scroll = false;
+        scroll(&mut app);
+        app.world.send_event(TouchpadMagnify(0.5));
+        assert_eq!(
+            zoom_and_scroll_events(&mut app),
+            [
+                egui::Event::Scroll(egui::vec2(0.0, 100.0)),
+                egui::Event::Zoom(0.5f32.exp())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_touch_drag_deadzone() {
+        use bevy::{
+            input::touch::{TouchInput, TouchPhase},
+            window::WindowFocused,
+        };
+
+        let (mut app, window) = headless_app_with_window();
+        app.insert_resource(EguiClock::Fixed(0.01));
+        app.world.resource_mut::<EguiSettings>().touch_drag_deadzone = 5.0;
+        app.update();
+        app.world.send_event(WindowFocused {
+            window,
+            focused: true,
+        });
+
+        fn touch(app: &mut App, phase: TouchPhase, x: f32) -> Option<egui::Pos2> {
+            app.world.send_event(TouchInput {
+                phase,
+                position: Vec2::new(x, 10.0),
+                force: None,
+                id: 0,
+            });
+            app.update();
+            let mut query = app.world.query::<&mut EguiContext>();
+            let mut ctx = query.single_mut(&mut app.world);
+            ctx.get_mut().input(|input| input.pointer.hover_pos())
+        }
+        assert_eq!(
+            touch(&mut app, TouchPhase::Started, 10.0),
+            Some(egui::pos2(10.0, 10.0))
+        );
+        // Moves inside the deadzone are withheld.
+        assert_eq!(
+            touch(&mut app, TouchPhase::Moved, 14.0),
+            Some(egui::pos2(10.0, 10.0))
+        );
+        assert_eq!(
+            touch(&mut app, TouchPhase::Moved, 20.0),
+            Some(egui::pos2(20.0, 10.0))
+        );
+        // Once the touch has left the deadzone, all moves are sent.
+        assert_eq!(
+            touch(&mut app, TouchPhase::Moved, 21.0),
+            Some(egui::pos2(21.0, 10.0))
+        );
+        assert_eq!(touch(&mut app, TouchPhase::Ended, 21.0), None);
+    }
+
+    #[test]
+    fn test_viewport_rect() {
+        use bevy::window::CursorMoved;
+
+        let (mut app, window) = headless_app_with_window();
+        app.world.resource_mut::<EguiSettings>().viewport_rect = Some(egui::Rect::from_min_max(
+            egui::pos2(100.0, 50.0),
+            egui::pos2(300.0, 250.0),
+        ));
+        app.update();
+
+        fn pointer_state(app: &mut App) -> (egui::Rect, Option<egui::Pos2>) {
+            let mut query = app.world.query::<&mut EguiContext>();
+            let mut ctx = query.single_mut(&mut app.world);
+            let ctx = ctx.get_mut();
+            (
+                ctx.screen_rect(),
+                ctx.input(|input| input.pointer.hover_pos()),
+            )
+        }
+
+        app.world.send_event(CursorMoved {
+            window,
+            position: Vec2::new(150.0, 100.0),
+        });
+        app.update();
+        assert_eq!(
+            pointer_state(&mut app),
+            (
+                egui::Rect::from_min_max(egui::pos2(100.0, 50.0), egui::pos2(300.0, 250.0)),
+                Some(egui::pos2(150.0, 100.0))
             )
-            .add_plugins(EguiPlugin)
-            .update();
+        );
+
+        app.world.send_event(CursorMoved {
+            window,
+            position: Vec2::new(50.0, 100.0),
+        });
+        app.update();
+        assert_eq!(pointer_state(&mut app).1, None);
+        assert_eq!(app.world.resource::<EguiMousePosition>().0, None);
+    }
+
+    #[test]
+    fn test_custom_context() {
+        let mut app = headless_app();
+        let ctx = egui::Context::default();
+        ctx.data_mut(|data| data.insert_temp(egui::Id::new("test"), 42u32));
+        let window = app
+            .world
+            .spawn((Window::default(), EguiContext::new(ctx.clone())))
+            .id();
+        app.update();
+
+        let mut contexts = app
+            .world
+            .query::<(&mut EguiContext, &EguiInput, &WindowSize)>();
+        let (mut window_ctx, _, _) = contexts.get_mut(&mut app.world, window).unwrap();
+        let window_ctx = window_ctx.get_mut();
+        assert_eq!(
+            window_ctx.data(|data| data.get_temp::<u32>(egui::Id::new("test"))),
+            Some(42)
+        );
+        // The frame driven by `bevy_egui` has been run for the adopted context.
+        assert_eq!(ctx.frame_nr(), 1);
+    }
+
+    #[cfg(feature = "render")]
+    #[test]
+    fn test_generate_mipmaps() {
+        // Alternating black and white columns alias when sampled without mipmaps.
+        let rgba: Vec<u8> = (0..8)
+            .flat_map(|x| {
+                let value = if x % 2 == 0 { 0 } else { 255 };
+                [value, value, value, 255]
+            })
+            .collect();
+        let image = Image::new(
+            Extent3d {
+                width: 8,
+                height: 4,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            rgba.repeat(4),
+            TextureFormat::Rgba8UnormSrgb,
+        );
+
+        let mipmapped_image = egui_node::generate_mipmaps(&image).unwrap();
+        assert_eq!(mipmapped_image.texture_descriptor.mip_level_count, 4);
+        // 8x4, 4x2, 2x1 and 1x1 pixels.
+        assert_eq!(mipmapped_image.data.len(), (32 + 8 + 2 + 1) * 4);
+        assert_eq!(mipmapped_image.data[..32 * 4], image.data[..]);
+        // The columns are averaged in linear space.
+        let gray = egui::ecolor::gamma_u8_from_linear_f32(0.5);
+        for pixel in mipmapped_image.data[32 * 4..].chunks(4) {
+            assert_eq!(pixel, [gray, gray, gray, 255]);
+        }
+
+        let mut array_image = image.clone();
+        array_image.texture_descriptor.size.depth_or_array_layers = 2;
+        assert!(egui_node::generate_mipmaps(&array_image).is_none());
+    }
+
+    #[cfg(feature = "render")]
+    #[test]
+    fn test_user_texture_mipmaps() {
+        let mut app = headless_app();
+        let id = app.world.resource_scope(
+            |world, mut user_textures: bevy::prelude::Mut<EguiUserTextures>| {
+                user_textures.add_rgba(
+                    &mut world.resource_mut::<Assets<Image>>(),
+                    4,
+                    4,
+                    vec![255; 4 * 4 * 4],
+                    EguiUserTextureOptions {
+                        generate_mipmaps: true,
+                        ..Default::default()
+                    },
+                )
+            },
+        );
+        app.update();
+
+        let user_textures = app.world.resource::<EguiUserTextures>();
+        let mipmapped_image = user_textures.mipmapped_images[&id.id()].id();
+        let images = app.world.resource::<Assets<Image>>();
+        assert_eq!(
+            images
+                .get(mipmapped_image)
+                .unwrap()
+                .texture_descriptor
+                .mip_level_count,
+            3
+        );
+        let options = user_textures
+            .image_options(user_textures.image_handle(id).unwrap())
+            .unwrap();
+        assert!(matches!(
+            options.sampler_descriptor().unwrap().mipmap_filter,
+            bevy::render::texture::ImageFilterMode::Linear
+        ));
+
+        let source = user_textures.image_handle(id).unwrap().clone();
+        let mut user_textures = app.world.resource_mut::<EguiUserTextures>();
+        let moved_id = user_textures
+            .add_image_with_id(
+                source.clone(),
+                100,
+                EguiUserTextureOptions {
+                    generate_mipmaps: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(!user_textures.mipmapped_images.contains_key(&id.id()));
+        app.update();
+        assert!(app
+            .world
+            .resource::<EguiUserTextures>()
+            .mipmapped_images
+            .contains_key(&moved_id.id()));
+
+        app.world
+            .resource_mut::<EguiUserTextures>()
+            .remove_image(&source);
+        // Dropped handles free their assets when the next frame begins.
+        app.update();
+        app.update();
+        assert!(app
+            .world
+            .resource::<EguiUserTextures>()
+            .mipmapped_images
+            .is_empty());
+        assert!(app
+            .world
+            .resource::<Assets<Image>>()
+            .get(mipmapped_image)
+            .is_none());
+    }
+
+    #[test]
+    fn test_widget_focus() {
+        let (mut app, window) = headless_app_with_window();
+        let text_edit_id = egui::Id::new("text_edit");
+        app.add_systems(bevy::app::Update, move |mut contexts: EguiContexts| {
+            egui::CentralPanel::default().show(contexts.ctx_mut(), |ui| {
+                let mut text = String::new();
+                ui.add(egui::TextEdit::singleline(&mut text).id(text_edit_id));
+            });
+        });
+        app.update();
+
+        fn request_focus(app: &mut App, window: Entity, id: egui::Id) {
+            let mut state = bevy::ecs::system::SystemState::<EguiContexts>::new(&mut app.world);
+            state.get_mut(&mut app.world).request_focus(window, id);
+        }
+        fn focused_widget(app: &mut App, window: Entity) -> Option<egui::Id> {
+            let mut state = bevy::ecs::system::SystemState::<EguiContexts>::new(&mut app.world);
+            state.get_mut(&mut app.world).focused_widget(window)
+        }
+        assert_eq!(focused_widget(&mut app, window), None);
+
+        request_focus(&mut app, window, text_edit_id);
+        app.update();
+        app.update();
+        assert_eq!(focused_widget(&mut app, window), Some(text_edit_id));
+
+        // The focus of a widget that isn't shown is dropped.
+        request_focus(&mut app, window, egui::Id::new("missing"));
+        app.update();
+        app.update();
+        assert_eq!(focused_widget(&mut app, window), None);
+
+        // Windows without contexts are ignored.
+        let other_window = app.world.spawn_empty().id();
+        request_focus(&mut app, other_window, text_edit_id);
+        assert_eq!(focused_widget(&mut app, other_window), None);
+    }
+
+    #[test]
+    fn test_cursor_icon_precedence() {
+        use bevy::window::{CursorIcon, CursorMoved, Window};
+
+        let (mut app, window) = headless_app_with_window();
+        app.add_systems(bevy::app::Update, |mut contexts: EguiContexts| {
+            egui::Area::new("area")
+                .fixed_pos(egui::Pos2::ZERO)
+                .show(contexts.ctx_mut(), |ui| {
+                    let mut text = String::new();
+                    // Sized explicitly, as the height of text is zero without fonts.
+                    ui.add_sized([100.0, 20.0], egui::TextEdit::singleline(&mut text));
+                });
+        });
+        app.update();
+        fn move_cursor(app: &mut App, window: Entity, position: Vec2) -> CursorIcon {
+            app.world.send_event(CursorMoved { window, position });
+            app.update();
+            app.world.get::<Window>(window).unwrap().cursor.icon
+        }
+        app.world.get_mut::<Window>(window).unwrap().cursor.icon = CursorIcon::Crosshair;
+
+        // The icon of the app is kept while Egui shows the default one.
+        assert_eq!(
+            move_cursor(&mut app, window, Vec2::new(500.0, 500.0)),
+            CursorIcon::Crosshair
+        );
+        // The icon of Egui wins while it hovers a widget.
+        assert_eq!(
+            move_cursor(&mut app, window, Vec2::new(10.0, 10.0)),
+            CursorIcon::Text
+        );
+        // And the icon of the app is restored afterwards.
+        assert_eq!(
+            move_cursor(&mut app, window, Vec2::new(500.0, 500.0)),
+            CursorIcon::Crosshair
+        );
+    }
+
+    #[test]
+    fn test_ime_position() {
+        use bevy::window::Window;
+
+        let (mut app, window) = headless_app_with_window();
+        let text_edit_id = egui::Id::new("text_edit");
+        app.add_systems(bevy::app::Update, move |mut contexts: EguiContexts| {
+            egui::Area::new("area")
+                .fixed_pos(egui::pos2(100.0, 50.0))
+                .show(contexts.ctx_mut(), |ui| {
+                    let mut text = String::new();
+                    ui.add(egui::TextEdit::singleline(&mut text).id(text_edit_id));
+                });
+        });
+        app.update();
+        let ime_position = |app: &App| app.world.get::<Window>(window).unwrap().ime_position;
+        assert_eq!(ime_position(&app), Vec2::ZERO);
+
+        let mut state = bevy::ecs::system::SystemState::<EguiContexts>::new(&mut app.world);
+        state
+            .get_mut(&mut app.world)
+            .request_focus(window, text_edit_id);
+        app.update();
+        app.update();
+        let position = ime_position(&app);
+        assert!(
+            (100.0..200.0).contains(&position.x) && (50.0..100.0).contains(&position.y),
+            "{position:?}"
+        );
+
+        // The position is in logical pixels, not in points.
+        app.world.resource_mut::<EguiSettings>().scale_factor = 2.0;
+        app.update();
+        app.update();
+        // (Egui rounds the text to the pixels, which are smaller now.)
+        let scaled_position = ime_position(&app);
+        assert!(
+            scaled_position.abs_diff_eq(position * 2.0, 1.0),
+            "{scaled_position:?}"
+        );
+    }
+
+    #[test]
+    fn test_user_texture_ids_survive_context_recreation() {
+        let (mut app, window) = headless_app_with_window();
+        let images = [
+            Handle::<Image>::weak_from_u128(1),
+            Handle::<Image>::weak_from_u128(2),
+        ];
+        let ids = {
+            let mut state = bevy::ecs::system::SystemState::<EguiContexts>::new(&mut app.world);
+            state
+                .get_mut(&mut app.world)
+                .add_images(&images, Default::default())
+        };
+
+        app.world
+            .entity_mut(window)
+            .insert(EguiContext::new(egui::Context::default()));
+        app.update();
+
+        let mut state = bevy::ecs::system::SystemState::<EguiContexts>::new(&mut app.world);
+        let mut contexts = state.get_mut(&mut app.world);
+        assert_eq!(contexts.ctx_for_window_mut(window).frame_nr(), 1);
+        for (image, id) in images.iter().zip(ids) {
+            assert_eq!(contexts.image_id(image), Some(id));
+            assert_eq!(contexts.add_image(image.clone()), id);
+        }
+    }
+
+    #[test]
+    fn test_follow_system_theme() {
+        use bevy::window::WindowThemeChanged;
+
+        let mut app = headless_app();
+        app.world.resource_mut::<EguiSettings>().follow_system_theme = true;
+        let window = app
+            .world
+            .query_filtered::<Entity, With<PrimaryWindow>>()
+            .single(&app.world);
+        // The theme the window is created with doesn't replace the visuals.
+        app.world.get_mut::<Window>(window).unwrap().window_theme = Some(WindowTheme::Light);
+        app.update();
+
+        fn theme_state(app: &mut App) -> (Option<WindowTheme>, bool) {
+            let mut query = app.world.query::<&mut EguiContext>();
+            let mut ctx = query.single_mut(&mut app.world);
+            let dark_mode = ctx.get_mut().style().visuals.dark_mode;
+            (ctx.system_theme(), dark_mode)
+        }
+        assert_eq!(theme_state(&mut app), (Some(WindowTheme::Light), true));
+
+        app.world.send_event(WindowThemeChanged {
+            window,
+            theme: WindowTheme::Dark,
+        });
+        app.update();
+        assert_eq!(theme_state(&mut app), (Some(WindowTheme::Dark), true));
+        app.world.send_event(WindowThemeChanged {
+            window,
+            theme: WindowTheme::Light,
+        });
+        app.update();
+        assert_eq!(theme_state(&mut app), (Some(WindowTheme::Light), false));
+
+        app.world.resource_mut::<EguiSettings>().follow_system_theme = false;
+        app.world.send_event(WindowThemeChanged {
+            window,
+            theme: WindowTheme::Dark,
+        });
+        app.update();
+        assert_eq!(theme_state(&mut app), (Some(WindowTheme::Dark), false));
+    }
+
+    #[test]
+    fn test_ui_sees_state_of_the_same_frame() {
+        #[derive(Resource, Default)]
+        struct Offset(f32);
+
+        fn update_offset_system(mut offset: ResMut<Offset>) {
+            offset.0 += 10.0;
+        }
+
+        fn ui_system(mut contexts: EguiContexts, offset: bevy::prelude::Res<Offset>) {
+            contexts
+                .ctx_mut()
+                .layer_painter(egui::LayerId::background())
+                .rect_filled(
+                    egui::Rect::from_min_size(egui::pos2(offset.0, 0.0), egui::vec2(5.0, 5.0)),
+                    0.0,
+                    egui::Color32::WHITE,
+                );
+        }
+
+        let mut app = headless_app();
+        app.init_resource::<Offset>().add_systems(
+            bevy::app::Update,
+            (update_offset_system, ui_system.after(update_offset_system)),
+        );
+        for frame in 1..=3 {
+            app.update();
+            let mut query = app.world.query::<&EguiRenderOutput>();
+            let render_output = query.single(&app.world);
+            let min_x = render_output
+                .paint_jobs
+                .iter()
+                .filter_map(|primitive| match &primitive.primitive {
+                    egui::epaint::Primitive::Mesh(mesh) => Some(mesh),
+                    egui::epaint::Primitive::Callback(_) => None,
+                })
+                .flat_map(|mesh| mesh.vertices.iter().map(|vertex| vertex.pos.x))
+                .fold(f32::INFINITY, f32::min);
+            // Feathering extends the rectangle by half a pixel.
+            assert_eq!(min_x, frame as f32 * 10.0 - 0.5);
+        }
+    }
+
+    #[cfg(feature = "diagnostics_overlay")]
+    #[test]
+    fn test_diagnostics_overlay() {
+        use crate::diagnostics_overlay::{
+            EguiDiagnosticsOverlay, EguiDiagnosticsOverlayPlugin, OVERLAY_TEXTURE_ID_OFFSET,
+        };
+        use bevy::input::{
+            keyboard::{KeyCode, KeyboardInput},
+            ButtonState,
+        };
+
+        let (mut app, window) = headless_app_with_window();
+        app.add_plugins(EguiDiagnosticsOverlayPlugin);
+        let text_edit_id = egui::Id::new("text_edit");
+        app.add_systems(bevy::app::Update, move |mut contexts: EguiContexts| {
+            egui::CentralPanel::default().show(contexts.ctx_mut(), |ui| {
+                let mut text = String::new();
+                ui.add(egui::TextEdit::singleline(&mut text).id(text_edit_id));
+            });
+        });
+        app.update();
+        fn overlay_primitive_count(app: &mut App) -> usize {
+            let mut query = app.world.query::<&EguiRenderOutput>();
+            query
+                .single(&app.world)
+                .paint_jobs
+                .iter()
+                .filter(|clipped_primitive| match &clipped_primitive.primitive {
+                    egui::epaint::Primitive::Mesh(mesh) => {
+                        mesh.texture_id == egui::TextureId::Managed(OVERLAY_TEXTURE_ID_OFFSET)
+                    }
+                    egui::epaint::Primitive::Callback(_) => false,
+                })
+                .count()
+        }
+        fn press_f3(app: &mut App, window: Entity) {
+            for state in [ButtonState::Pressed, ButtonState::Released] {
+                app.world.send_event(KeyboardInput {
+                    scan_code: 0,
+                    key_code: Some(KeyCode::F3),
+                    state,
+                    window,
+                });
+                app.update();
+            }
+        }
+        assert_eq!(overlay_primitive_count(&mut app), 0);
+
+        press_f3(&mut app, window);
+        assert!(app.world.resource::<EguiDiagnosticsOverlay>().visible);
+        assert!(overlay_primitive_count(&mut app) > 0);
+        // The overlay doesn't live in the context of the window.
+        let mut query = app.world.query::<&mut EguiContext>();
+        let layer_count = query
+            .single_mut(&mut app.world)
+            .get_mut()
+            .memory(|memory| memory.layer_ids().count());
+        assert_eq!(layer_count, 1);
+
+        // The key is typed into a focused text edit instead of toggling the overlay.
+        let mut state = bevy::ecs::system::SystemState::<EguiContexts>::new(&mut app.world);
+        state
+            .get_mut(&mut app.world)
+            .request_focus(window, text_edit_id);
+        app.update();
+        press_f3(&mut app, window);
+        assert!(app.world.resource::<EguiDiagnosticsOverlay>().visible);
+    }
+
+    #[test]
+    fn test_font_atlas_info() {
+        let (mut app, window) = headless_app_with_window();
+
+        fn font_atlas_info(app: &mut App, window: Entity) -> EguiFontAtlasInfo {
+            let mut state = bevy::ecs::system::SystemState::<EguiContexts>::new(&mut app.world);
+            state.get_mut(&mut app.world).font_atlas_info(window)
+        }
+        let info = font_atlas_info(&mut app, window);
+        assert_eq!(info.version, 1);
+        assert_eq!(info.textures_delta.full_updates, 1);
+        assert!(info.size[0] > 0 && info.size[1] > 0);
+        assert!(info.fill_ratio > 0.0);
+        assert!(!info.overflowed);
+
+        app.update();
+        let info = font_atlas_info(&mut app, window);
+        assert_eq!(info.version, 1);
+        assert_eq!(info.textures_delta, EguiTexturesDeltaStats::default());
+    }
+
+    // Without the default fonts, no glyphs are rasterized into the atlas.
+    #[cfg(feature = "default_fonts")]
+    #[test]
+    fn test_font_atlas_overflow() {
+        let (mut app, window) = headless_app_with_window();
+        app.world.resource_mut::<EguiSettings>().max_texture_side = Some(512);
+        app.add_systems(bevy::app::Update, |mut contexts: EguiContexts| {
+            let text: String = ('!'..='~').collect();
+            egui::CentralPanel::default().show(contexts.ctx_mut(), |ui| {
+                ui.label(egui::RichText::new(text).size(200.0));
+            });
+        });
+        app.update();
+
+        let mut state = bevy::ecs::system::SystemState::<EguiContexts>::new(&mut app.world);
+        let info = state.get_mut(&mut app.world).font_atlas_info(window);
+        assert_eq!(info.size[0], 1024);
+        assert!(info.overflowed);
+    }
+
+    #[test]
+    fn test_egui_scale_factor_change() {
+        use bevy::window::CursorMoved;
+
+        let (mut app, window) = headless_app_with_window();
+
+        fn hover_pos(app: &mut App) -> Option<egui::Pos2> {
+            let mut query = app.world.query::<&mut EguiContext>();
+            let mut ctx = query.single_mut(&mut app.world);
+            ctx.get_mut().input(|input| input.pointer.hover_pos())
+        }
+        app.world.send_event(CursorMoved {
+            window,
+            position: Vec2::new(40.0, 20.0),
+        });
+        app.update();
+        assert_eq!(hover_pos(&mut app), Some(egui::pos2(40.0, 20.0)));
+
+        // Pointer events sent in the same frame as a scale change are mapped with the new scale.
+        app.world.resource_mut::<EguiSettings>().scale_factor = 2.0;
+        app.world.send_event(CursorMoved {
+            window,
+            position: Vec2::new(60.0, 20.0),
+        });
+        app.update();
+        assert_eq!(hover_pos(&mut app), Some(egui::pos2(30.0, 10.0)));
+
+        // The last known position follows the scale as well.
+        app.world.resource_mut::<EguiSettings>().scale_factor = 4.0;
+        app.update();
+        assert_eq!(
+            app.world.resource::<EguiMousePosition>().0,
+            Some((window, egui::vec2(15.0, 5.0)))
+        );
+    }
+
+    #[cfg(feature = "render")]
+    #[test]
+    fn test_closed_windows_teardown() {
+        let mut app = headless_app();
+        app.update();
+        let image_count = app.world.resource::<Assets<Image>>().len();
+        let managed_texture_count = app.world.resource::<EguiManagedTextures>().len();
+
+        for _ in 0..10 {
+            let window = app.world.spawn(Window::default()).id();
+            app.update();
+            assert!(app.world.resource::<EguiManagedTextures>().len() > managed_texture_count);
+
+            app.world.despawn(window);
+            app.world.send_event(WindowClosed { window });
+            app.update();
+            assert_eq!(
+                app.world.resource::<EguiManagedTextures>().len(),
+                managed_texture_count
+            );
+            assert_eq!(app.world.resource::<Assets<Image>>().len(), image_count);
+        }
+    }
+
+    #[cfg(feature = "render")]
+    #[test]
+    fn test_extracted_egui_frames() {
+        let (mut app, primary_window) = headless_app_with_window();
+        let second_window = app
+            .world
+            .spawn(Window {
+                resolution: bevy::window::WindowResolution::new(300.0, 200.0),
+                ..Default::default()
+            })
+            .id();
+        app.add_systems(bevy::app::Update, move |mut contexts: EguiContexts| {
+            egui::Window::new("Test").show(contexts.ctx_for_window_mut(second_window), |ui| {
+                ui.label("Test");
+            });
+        });
+        app.update();
+        app.update();
+
+        let mut extracted_frames = ExtractedEguiFrames::default();
+        let mut query = app
+            .world
+            .query::<(Entity, &WindowSize, &EguiRenderOutput, &EguiContext)>();
+        extracted_frames.extract(query.iter(&app.world));
+
+        assert_eq!(extracted_frames.len(), 2);
+        let primary_frame = extracted_frames.get(&primary_window).unwrap();
+        assert!(primary_frame.paint_jobs.is_empty());
+        let second_frame = extracted_frames.get(&second_window).unwrap();
+        assert!(!second_frame.paint_jobs.is_empty());
+        assert!(!second_frame.frame_skipped);
+        assert_eq!(second_frame.window_size, WindowSize::new(300.0, 200.0, 1.0));
+
+        // The output of unfocused windows isn't resubmitted if their frames are skipped.
+        app.world
+            .resource_mut::<EguiSettings>()
+            .render_when_unfocused = false;
+        app.world.get_mut::<Window>(second_window).unwrap().focused = false;
+        app.update();
+        assert!(!app
+            .world
+            .get::<EguiRenderOutput>(second_window)
+            .unwrap()
+            .paint_jobs
+            .is_empty());
+        extracted_frames.extract(query.iter(&app.world));
+        let second_frame = extracted_frames.get(&second_window).unwrap();
+        assert!(second_frame.frame_skipped);
+        assert!(second_frame.paint_jobs.is_empty());
+
+        let mut render_world = bevy::ecs::world::World::new();
+        render_world.insert_resource(extracted_frames);
+        let mut node = egui_node::EguiNode::new(second_window);
+        bevy::render::render_graph::Node::update(&mut node, &mut render_world);
+        assert!(node.draw_commands().is_empty());
     }
 }
@@ -1,9 +1,12 @@
 use crate::{
     egui_node::{EguiNode, EguiPipeline, EguiPipelineKey},
-    EguiManagedTextures, EguiSettings, EguiUserTextures, WindowSize,
+    node, EguiColormap, EguiContext, EguiCubemapFace, EguiManagedTextures, EguiMask,
+    EguiRenderCapabilities, EguiRenderOutput, EguiSettings, EguiUserTextureOptions,
+    EguiUserTextures, WindowSize, MIN_MAX_TEXTURE_SIDE,
 };
 use bevy::{
     ecs::system::SystemParam,
+    log,
     prelude::*,
     render::{
         extract_resource::ExtractResource,
@@ -11,14 +14,20 @@ use bevy::{
         render_graph::RenderGraph,
         render_resource::{
             BindGroup, BindGroupEntry, BindingResource, BufferId, CachedRenderPipelineId,
-            DynamicUniformBuffer, PipelineCache, ShaderType, SpecializedRenderPipelines,
+            DynamicUniformBuffer, PipelineCache, Sampler, SpecializedRenderPipelines,
+            TextureFormat, TextureView, TextureViewDescriptor, TextureViewDimension,
         },
         renderer::{RenderDevice, RenderQueue},
-        texture::Image,
+        settings::WgpuFeatures,
+        texture::{
+            FallbackImage, Image, ImageAddressMode, ImageFilterMode, ImageSampler,
+            ImageSamplerDescriptor,
+        },
         view::ExtractedWindows,
         Extract,
     },
-    utils::HashMap,
+    utils::{HashMap, HashSet},
+    window::WindowClosed,
 };
 
 /// Extracted Egui settings.
@@ -36,8 +45,75 @@ impl ExtractResource for ExtractedEguiManagedTextures {
     }
 }
 
+/// Egui output of a window, extracted for rendering.
+#[derive(Debug)]
+pub struct ExtractedEguiFrame {
+    /// Physical size and scale factor of the window.
+    pub window_size: WindowSize,
+    /// Pairs of rectangles and paint commands (see [`EguiRenderOutput::paint_jobs`]).
+    ///
+    /// They are taken by the [`EguiNode`] of the window in [`Node::update`], so they are empty
+    /// during the render graph run.
+    ///
+    /// [`Node::update`]: bevy::render::render_graph::Node::update
+    pub paint_jobs: Vec<egui::ClippedPrimitive>,
+    /// The change in Egui textures since the last frame (see
+    /// [`EguiRenderOutput::textures_delta`]).
+    pub textures_delta: egui::TexturesDelta,
+    /// Whether the frame of the window is skipped (see [`EguiContext::is_frame_skipped`]), in
+    /// which case `paint_jobs` are empty and the window isn't drawn into.
+    pub frame_skipped: bool,
+}
+
+/// Egui output of all windows, extracted for rendering.
+///
+/// The resource is refilled by [`extract_egui_frames_system`] every frame, render systems and
+/// nodes read the output of every window from it.
+#[derive(Resource, Deref, DerefMut, Debug, Default)]
+pub struct ExtractedEguiFrames(pub HashMap<Entity, ExtractedEguiFrame>);
+
+impl ExtractedEguiFrames {
+    pub(crate) fn extract<'a>(
+        &mut self,
+        windows: impl Iterator<
+            Item = (
+                Entity,
+                &'a WindowSize,
+                &'a EguiRenderOutput,
+                &'a EguiContext,
+            ),
+        >,
+    ) {
+        self.0.clear();
+        self.0
+            .extend(windows.map(|(window, window_size, render_output, ctx)| {
+                let frame_skipped = ctx.is_frame_skipped();
+                let frame = ExtractedEguiFrame {
+                    window_size: *window_size,
+                    // The output of a skipped frame has already been drawn.
+                    paint_jobs: if frame_skipped {
+                        Vec::new()
+                    } else {
+                        render_output.paint_jobs.clone()
+                    },
+                    textures_delta: render_output.textures_delta.clone(),
+                    frame_skipped,
+                };
+                (window, frame)
+            }));
+    }
+}
+
+/// Extracts Egui output of all windows into [`ExtractedEguiFrames`].
+pub fn extract_egui_frames_system(
+    mut extracted_frames: ResMut<ExtractedEguiFrames>,
+    windows: Extract<Query<(Entity, &WindowSize, &EguiRenderOutput, &EguiContext)>>,
+) {
+    extracted_frames.extract(windows.iter());
+}
+
 /// Corresponds to Egui's [`egui::TextureId`].
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum EguiTextureId {
     /// Textures allocated via Egui.
     Managed(Entity, u64),
@@ -73,12 +149,14 @@ impl ExtractedEguiTextures<'_> {
 }
 
 /// Sets up the pipeline for newly created windows.
+///
+/// See [`node::egui_pass`] for the label and the edges of the added node.
 pub fn setup_new_windows_render_system(
     windows: Extract<Query<Entity, Added<Window>>>,
     mut render_graph: ResMut<RenderGraph>,
 ) {
     for window in windows.iter() {
-        let egui_pass = format!("egui-{}-{}", window.index(), window.generation());
+        let egui_pass = node::egui_pass(window);
 
         let new_node = EguiNode::new(window);
 
@@ -91,44 +169,92 @@ pub fn setup_new_windows_render_system(
     }
 }
 
+/// Removes the render graph nodes of closed windows.
+pub fn teardown_closed_windows_render_system(
+    mut closed_windows: Extract<EventReader<WindowClosed>>,
+    mut render_graph: ResMut<RenderGraph>,
+) {
+    for event in closed_windows.read() {
+        if let Err(err) = render_graph.remove_node(node::egui_pass(event.window)) {
+            log::error!(
+                "Failed to remove the Egui pass for a closed window ({:?}): {:?}",
+                event.window,
+                err
+            );
+        }
+    }
+}
+
 /// Describes the transform buffer.
 #[derive(Resource, Default)]
 pub struct EguiTransforms {
-    /// Uniform buffer.
-    pub buffer: DynamicUniformBuffer<EguiTransform>,
+    /// Uniform buffer of [`EguiTransform::uniform`] values.
+    pub buffer: DynamicUniformBuffer<[Vec4; 2]>,
     /// Offsets for each window.
     pub offsets: HashMap<Entity, u32>,
     /// Bind group.
     pub bind_group: Option<(BufferId, BindGroup)>,
 }
 
-/// Scale and translation for rendering Egui shapes. Is needed to transform Egui coordinates from
-/// the screen space with the center at (0, 0) to the normalised viewport space.
-#[derive(ShaderType, Default)]
+/// Scale and translation for rendering Egui shapes. Is needed to transform Egui coordinates
+/// from the screen space with the center at (0, 0) to the normalised viewport space.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct EguiTransform {
     /// Is affected by window size and [`EguiSettings::scale_factor`].
     pub scale: Vec2,
     /// Normally equals `Vec2::new(-1.0, 1.0)`.
     pub translation: Vec2,
+    /// [`EguiSettings::global_tint`] in linear RGBA.
+    pub tint: Vec4,
+}
+
+impl Default for EguiTransform {
+    fn default() -> Self {
+        EguiTransform {
+            scale: Vec2::ZERO,
+            translation: Vec2::ZERO,
+            tint: Vec4::ONE,
+        }
+    }
 }
 
 impl EguiTransform {
-    /// Calculates the transform from window size and scale factor.
-    pub fn from_window_size(window_size: WindowSize, scale_factor: f32) -> Self {
+    /// Calculates the transform from window size, scale factor and tint (in linear RGBA).
+    pub fn new(window_size: WindowSize, scale_factor: f32, tint: Vec4) -> Self {
         EguiTransform {
             scale: Vec2::new(
                 2.0 / (window_size.width() / scale_factor),
                 -2.0 / (window_size.height() / scale_factor),
             ),
             translation: Vec2::new(-1.0, 1.0),
+            tint,
         }
     }
+
+    /// Calculates the transform from window size and scale factor, without a tint.
+    pub fn from_window_size(window_size: WindowSize, scale_factor: f32) -> Self {
+        Self::new(window_size, scale_factor, Vec4::ONE)
+    }
+
+    /// Returns the transform laid out as the `Transform` uniform of the shader: the scale and
+    /// the translation share the first vector, the tint is the second one.
+    pub fn uniform(&self) -> [Vec4; 2] {
+        [
+            Vec4::new(
+                self.scale.x,
+                self.scale.y,
+                self.translation.x,
+                self.translation.y,
+            ),
+            self.tint,
+        ]
+    }
 }
 
 /// Prepares Egui transforms.
 pub fn prepare_egui_transforms_system(
     mut egui_transforms: ResMut<EguiTransforms>,
-    window_sizes: Query<(Entity, &WindowSize)>,
+    extracted_frames: Res<ExtractedEguiFrames>,
     egui_settings: Res<EguiSettings>,
 
     render_device: Res<RenderDevice>,
@@ -139,11 +265,12 @@ pub fn prepare_egui_transforms_system(
     egui_transforms.buffer.clear();
     egui_transforms.offsets.clear();
 
-    for (window, size) in window_sizes.iter() {
-        let offset = egui_transforms.buffer.push(EguiTransform::from_window_size(
-            *size,
-            egui_settings.scale_factor as f32,
-        ));
+    let tint = Vec4::from(egui_settings.global_tint.as_linear_rgba_f32());
+    for (&window, frame) in extracted_frames.iter() {
+        let offset = egui_transforms.buffer.push(
+            EguiTransform::new(frame.window_size, egui_settings.scale_factor as f32, tint)
+                .uniform(),
+        );
         egui_transforms.offsets.insert(window, offset);
     }
 
@@ -173,20 +300,402 @@ pub fn prepare_egui_transforms_system(
 #[derive(Resource, Deref, DerefMut, Default)]
 pub struct EguiTextureBindGroups(pub HashMap<EguiTextureId, BindGroup>);
 
+/// Maps Egui textures to the GPU texture views they are displayed with.
+///
+/// A view is the one of the texture's GPU image (or of its copy with
+/// [`EguiUserTextureOptions::generate_mipmaps`]), of the cubemap face selected with
+/// [`EguiUserTextureOptions::cubemap_face`] or the one supplied by an [`EguiTextureProvider`].
+/// Textures whose images aren't uploaded yet have no views.
+///
+/// The resource lives in the render world and is rebuilt every frame by
+/// [`queue_bind_groups_system`] (in [`RenderSet::Queue`](bevy::render::RenderSet::Queue)), so
+/// the views are only valid for the render systems and nodes that run after that set in the same
+/// frame.
+#[derive(Resource, Deref, DerefMut, Default)]
+pub struct EguiTextureViews(pub HashMap<EguiTextureId, TextureView>);
+
+impl EguiTextureViews {
+    /// Returns the view a texture is displayed with.
+    #[must_use]
+    pub fn egui_texture_view(&self, id: EguiTextureId) -> Option<TextureView> {
+        self.0.get(&id).cloned()
+    }
+}
+
+/// A GPU texture supplied by an [`EguiTextureProvider`].
+pub struct EguiProvidedTexture {
+    /// The view Egui samples.
+    pub texture_view: TextureView,
+    /// The sampler Egui samples the view with.
+    pub sampler: Sampler,
+}
+
+/// Supplies GPU textures for Egui user textures instead of the textures uploaded for their
+/// Bevy images.
+///
+/// A provider lives in the render world (see [`EguiUserTextureProvider`]) and is called from
+/// [`queue_bind_groups_system`], so it runs on the render thread and only has access to what it
+/// owns and the [`RenderDevice`]. It's asked for every registered user texture every frame, so it
+/// should cache what it creates; [`TextureView`] and [`Sampler`] are cheap to clone. Returning
+/// `None` keeps the default behavior for the texture: the GPU image of its Bevy image is used.
+///
+/// UV flips and colormaps of [`EguiUserTextureOptions`] are still applied to provided textures,
+/// [`EguiUserTextureOptions::sampler`], [`EguiUserTextureOptions::generate_mipmaps`] and
+/// [`EguiUserTextureOptions::cubemap_face`] are left to the provider.
+pub trait EguiTextureProvider: Send + Sync + 'static {
+    /// Returns the texture to display for the user texture `id`, registered for the `image` asset.
+    fn provide_texture(
+        &mut self,
+        id: u64,
+        image: AssetId<Image>,
+        options: &EguiUserTextureOptions,
+        render_device: &RenderDevice,
+    ) -> Option<EguiProvidedTexture>;
+
+    /// Is called once a user texture that has been provided is removed from
+    /// [`EguiUserTextures`]. Resources kept for it can be released.
+    fn free_texture(&mut self, id: u64) {
+        let _ = id;
+    }
+}
+
+/// The [`EguiTextureProvider`] used for user textures.
+///
+/// Insert it into the render app to register a provider:
+///
+/// ```no_run
+/// # use bevy::{prelude::*, render::{renderer::RenderDevice, RenderApp}};
+/// # use bevy_egui::{render_systems::*, EguiUserTextureOptions};
+/// struct AtlasProvider;
+///
+/// impl EguiTextureProvider for AtlasProvider {
+///     fn provide_texture(
+///         &mut self,
+///         _id: u64,
+///         _image: AssetId<Image>,
+///         _options: &EguiUserTextureOptions,
+///         _render_device: &RenderDevice,
+///     ) -> Option<EguiProvidedTexture> {
+///         None
+///     }
+/// }
+///
+/// # let mut app = App::new();
+/// app.sub_app_mut(RenderApp)
+///     .insert_resource(EguiUserTextureProvider::new(AtlasProvider));
+/// ```
+///
+/// Without this resource, user textures use the GPU images of their Bevy images.
+#[derive(Resource)]
+pub struct EguiUserTextureProvider {
+    provider: Box<dyn EguiTextureProvider>,
+    provided_textures: HashSet<u64>,
+}
+
+impl EguiUserTextureProvider {
+    /// Wraps a provider.
+    pub fn new(provider: impl EguiTextureProvider) -> Self {
+        Self {
+            provider: Box::new(provider),
+            provided_textures: HashSet::default(),
+        }
+    }
+
+    fn provide_texture(
+        &mut self,
+        id: u64,
+        image: AssetId<Image>,
+        options: &EguiUserTextureOptions,
+        render_device: &RenderDevice,
+    ) -> Option<EguiProvidedTexture> {
+        let texture = self
+            .provider
+            .provide_texture(id, image, options, render_device)?;
+        self.provided_textures.insert(id);
+        Some(texture)
+    }
+
+    fn free_removed_textures(&mut self, user_textures: &EguiUserTextures) {
+        let provider = &mut self.provider;
+        self.provided_textures.retain(|id| {
+            let is_registered = user_textures.images.contains_key(id);
+            if !is_registered {
+                provider.free_texture(*id);
+            }
+            is_registered
+        });
+    }
+}
+
+/// Samplers created for user textures that override the sampler of their image
+/// (see [`EguiUserTextureOptions::sampler`](crate::EguiUserTextureOptions::sampler)).
+#[derive(Resource, Deref, DerefMut, Default)]
+pub struct EguiUserTextureSamplers(pub HashMap<u64, (ImageSamplerDescriptor, Sampler)>);
+
+impl EguiUserTextureSamplers {
+    fn get_or_create(
+        &mut self,
+        id: u64,
+        descriptor: &ImageSamplerDescriptor,
+        render_device: &RenderDevice,
+    ) -> Option<&Sampler> {
+        let is_outdated = self
+            .0
+            .get(&id)
+            .map_or(true, |(cached, _)| cached.as_wgpu() != descriptor.as_wgpu());
+        if is_outdated {
+            if descriptor.anisotropy_clamp > 1 && !is_filtering_linear(descriptor) {
+                log::error!(
+                    "Anisotropic filtering requires all filter modes to be linear (user texture id: {})",
+                    id
+                );
+                return None;
+            }
+            if uses_clamp_to_border(descriptor)
+                && !render_device
+                    .features()
+                    .contains(WgpuFeatures::ADDRESS_MODE_CLAMP_TO_BORDER)
+            {
+                log::error!(
+                    "The render device doesn't support `ImageAddressMode::ClampToBorder`, falling back to the image sampler (user texture id: {})",
+                    id
+                );
+                return None;
+            }
+            let sampler = render_device.create_sampler(&descriptor.as_wgpu());
+            self.0.insert(id, (descriptor.clone(), sampler));
+        }
+        self.0.get(&id).map(|(_, sampler)| sampler)
+    }
+}
+
+/// Validates the render device limits and features the Egui pipeline relies on.
+///
+/// The default sampler from [`EguiSettings::sampler_descriptor`] is downgraded to a compatible
+/// one if the device can't create it, so that creating Egui textures doesn't panic.
+pub(crate) fn check_render_capabilities(
+    render_device: &RenderDevice,
+    egui_settings: &mut EguiSettings,
+) -> EguiRenderCapabilities {
+    let max_texture_dimension_2d = render_device.limits().max_texture_dimension_2d;
+    let rendering_enabled = max_texture_dimension_2d as usize >= MIN_MAX_TEXTURE_SIDE;
+    if !rendering_enabled {
+        log::error!(
+            "Egui rendering is disabled: the render device supports textures of up to {max_texture_dimension_2d}px, while at least {MIN_MAX_TEXTURE_SIDE}px are required"
+        );
+    }
+
+    if let ImageSampler::Descriptor(descriptor) = &mut egui_settings.sampler_descriptor {
+        if !render_device
+            .features()
+            .contains(WgpuFeatures::ADDRESS_MODE_CLAMP_TO_BORDER)
+        {
+            for address_mode in [
+                &mut descriptor.address_mode_u,
+                &mut descriptor.address_mode_v,
+                &mut descriptor.address_mode_w,
+            ] {
+                if matches!(address_mode, ImageAddressMode::ClampToBorder) {
+                    log::error!("The render device doesn't support `ImageAddressMode::ClampToBorder`, falling back to `ClampToEdge` for Egui textures");
+                    *address_mode = ImageAddressMode::ClampToEdge;
+                }
+            }
+        }
+        if descriptor.anisotropy_clamp > 1 && !is_filtering_linear(descriptor) {
+            log::error!("Anisotropic filtering requires all filter modes to be linear, disabling it for Egui textures");
+            descriptor.anisotropy_clamp = 1;
+        }
+    }
+
+    EguiRenderCapabilities {
+        max_texture_side: max_texture_dimension_2d as usize,
+        rendering_enabled,
+    }
+}
+
+fn uses_clamp_to_border(descriptor: &ImageSamplerDescriptor) -> bool {
+    [
+        descriptor.address_mode_u,
+        descriptor.address_mode_v,
+        descriptor.address_mode_w,
+    ]
+    .into_iter()
+    .any(|address_mode| matches!(address_mode, ImageAddressMode::ClampToBorder))
+}
+
+fn is_filtering_linear(descriptor: &ImageSamplerDescriptor) -> bool {
+    [
+        descriptor.mag_filter,
+        descriptor.min_filter,
+        descriptor.mipmap_filter,
+    ]
+    .into_iter()
+    .all(|filter| matches!(filter, ImageFilterMode::Linear))
+}
+
 /// Queues bind groups.
+///
+/// User textures are taken from the [`EguiUserTextureProvider`] if there's one and it provides
+/// them.
+#[allow(clippy::too_many_arguments)]
 pub fn queue_bind_groups_system(
     mut commands: Commands,
     egui_textures: ExtractedEguiTextures,
     render_device: Res<RenderDevice>,
     gpu_images: Res<RenderAssets<Image>>,
+    fallback_image: Res<FallbackImage>,
+    egui_settings: Res<EguiSettings>,
     egui_pipeline: Res<EguiPipeline>,
+    mut user_texture_samplers: ResMut<EguiUserTextureSamplers>,
+    mut reported_invalid_cubemaps: Local<HashSet<u64>>,
+    mut texture_provider: Option<ResMut<EguiUserTextureProvider>>,
 ) {
+    let user_texture_options = &egui_textures.user_textures.options;
+    user_texture_samplers.retain(|id, _| {
+        user_texture_options
+            .get(id)
+            .is_some_and(|options| options.sampler_descriptor().is_some())
+    });
+    if let Some(texture_provider) = texture_provider.as_deref_mut() {
+        texture_provider.free_removed_textures(&egui_textures.user_textures);
+    }
+    let default_options = EguiUserTextureOptions::default();
+
+    let create_bind_group = |texture_view: &TextureView, sampler: &Sampler| {
+        render_device.create_bind_group(
+            None,
+            &egui_pipeline.texture_bind_group_layout,
+            &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(texture_view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(sampler),
+                },
+            ],
+        )
+    };
+
+    let mut texture_views = HashMap::default();
     let bind_groups = egui_textures
         .handles()
         .filter_map(|(texture, handle_id)| {
-            let gpu_image = gpu_images.get(&Handle::Weak(handle_id))?;
+            if let (EguiTextureId::User(id), Some(texture_provider)) =
+                (&texture, texture_provider.as_deref_mut())
+            {
+                let options = user_texture_options.get(id).unwrap_or(&default_options);
+                if let Some(provided) =
+                    texture_provider.provide_texture(*id, handle_id, options, &render_device)
+                {
+                    let bind_group =
+                        create_bind_group(&provided.texture_view, &provided.sampler);
+                    texture_views.insert(texture, provided.texture_view);
+                    return Some((texture, bind_group));
+                }
+            }
+            let image_id = match texture {
+                EguiTextureId::User(id) => egui_textures
+                    .user_textures
+                    .mipmapped_images
+                    .get(&id)
+                    .map_or(handle_id, Handle::id),
+                EguiTextureId::Managed(_, _) => handle_id,
+            };
+            let gpu_image = match (gpu_images.get(Handle::Weak(image_id)), &texture) {
+                (Some(gpu_image), _) => gpu_image,
+                // The image is still loading (or has failed to load).
+                (None, EguiTextureId::User(_)) if egui_settings.user_texture_fallback => {
+                    return Some((
+                        texture,
+                        create_bind_group(
+                            &fallback_image.d2.texture_view,
+                            &fallback_image.d2.sampler,
+                        ),
+                    ));
+                }
+                (None, _) => return None,
+            };
+            let cubemap_face = match texture {
+                EguiTextureId::User(id) => user_texture_options
+                    .get(&id)
+                    .and_then(|options| Some((id, options.cubemap_face?))),
+                EguiTextureId::Managed(_, _) => None,
+            };
+            let face_texture_view;
+            let texture_view = match cubemap_face {
+                Some((id, face)) => {
+                    let array_layers = gpu_image.texture.depth_or_array_layers();
+                    let Some(descriptor) = cubemap_face_view_descriptor(face, array_layers) else {
+                        if reported_invalid_cubemaps.insert(id) {
+                            log::error!(
+                                "User texture {} is displayed as a cubemap face, but its image has {} array layers instead of 6",
+                                id,
+                                array_layers
+                            );
+                        }
+                        return None;
+                    };
+                    face_texture_view = gpu_image.texture.create_view(&descriptor);
+                    &face_texture_view
+                }
+                None => &gpu_image.texture_view,
+            };
+            let sampler_descriptor = match texture {
+                EguiTextureId::User(id) => user_texture_options
+                    .get(&id)
+                    .and_then(|options| Some((id, options.sampler_descriptor()?))),
+                EguiTextureId::Managed(_, _) => None,
+            };
+            let sampler = sampler_descriptor
+                .and_then(|(id, descriptor)| {
+                    user_texture_samplers.get_or_create(id, &descriptor, &render_device)
+                })
+                .unwrap_or(&gpu_image.sampler);
+            let bind_group = create_bind_group(texture_view, sampler);
+            texture_views.insert(texture, texture_view.clone());
+            Some((texture, bind_group))
+        })
+        .collect();
+
+    commands.insert_resource(EguiTextureBindGroups(bind_groups));
+    commands.insert_resource(EguiTextureViews(texture_views));
+}
+
+/// Returns the descriptor of the view displaying `face` of a texture with `array_layers` array
+/// layers, or `None` if the texture isn't a cubemap.
+pub(crate) fn cubemap_face_view_descriptor(
+    face: EguiCubemapFace,
+    array_layers: u32,
+) -> Option<TextureViewDescriptor<'static>> {
+    (array_layers == 6).then(|| TextureViewDescriptor {
+        label: Some("egui cubemap face view"),
+        dimension: Some(TextureViewDimension::D2),
+        base_array_layer: face.array_layer(),
+        array_layer_count: Some(1),
+        ..Default::default()
+    })
+}
+
+/// Maps windows to the bind groups of their [`EguiMask`] images.
+#[derive(Resource, Deref, DerefMut, Default)]
+pub struct EguiMaskBindGroups(pub HashMap<Entity, BindGroup>);
+
+/// Queues the bind groups of window masks whose images are loaded.
+pub fn queue_mask_bind_groups_system(
+    mut commands: Commands,
+    masks: Query<(Entity, &EguiMask)>,
+    render_device: Res<RenderDevice>,
+    gpu_images: Res<RenderAssets<Image>>,
+    egui_pipeline: Res<EguiPipeline>,
+) {
+    let bind_groups = masks
+        .iter()
+        .filter_map(|(window, mask)| {
+            let gpu_image = gpu_images.get(&mask.0)?;
             let bind_group = render_device.create_bind_group(
-                None,
+                Some("egui mask bind group"),
                 &egui_pipeline.texture_bind_group_layout,
                 &[
                     BindGroupEntry {
@@ -199,36 +708,77 @@ pub fn queue_bind_groups_system(
                     },
                 ],
             );
-            Some((texture, bind_group))
+            Some((window, bind_group))
         })
         .collect();
 
-    commands.insert_resource(EguiTextureBindGroups(bind_groups))
+    commands.insert_resource(EguiMaskBindGroups(bind_groups));
 }
 
-/// Cached Pipeline IDs for the specialized `EguiPipeline`s
+/// Cached Pipeline IDs for the specialized `EguiPipeline`s, per window and colormap.
+///
+/// Pipelines for colormaps other than [`EguiColormap::Rgba`] are queued only if a registered user
+/// texture uses them.
 #[derive(Resource)]
-pub struct EguiPipelines(pub HashMap<Entity, CachedRenderPipelineId>);
+pub struct EguiPipelines(pub HashMap<(Entity, EguiColormap), CachedRenderPipelineId>);
+
+/// Swap chain formats that [`queue_pipelines_system`] specializes [`EguiPipeline`] for
+/// before any window is known, so that the first frame showing Egui doesn't wait for
+/// a pipeline to compile.
+pub const WARM_UP_TEXTURE_FORMATS: [TextureFormat; 2] =
+    [TextureFormat::Bgra8UnormSrgb, TextureFormat::Rgba8UnormSrgb];
 
 /// Queue [`EguiPipeline`]s specialized on each window's swap chain texture format.
+#[allow(clippy::too_many_arguments)]
 pub fn queue_pipelines_system(
     mut commands: Commands,
     pipeline_cache: Res<PipelineCache>,
     mut pipelines: ResMut<SpecializedRenderPipelines<EguiPipeline>>,
     egui_pipeline: Res<EguiPipeline>,
     windows: Res<ExtractedWindows>,
+    user_textures: Res<EguiUserTextures>,
+    masks: Query<(), With<EguiMask>>,
+    mut warmed_up: Local<bool>,
 ) {
-    let pipelines = windows
-        .iter()
-        .filter_map(|(window_id, window)| {
+    // Specialized pipelines are cached by their key, so the ones queued here are reused once
+    // a window with a matching format appears.
+    if !*warmed_up {
+        for texture_format in WARM_UP_TEXTURE_FORMATS {
+            pipelines.specialize(
+                &pipeline_cache,
+                &egui_pipeline,
+                EguiPipelineKey {
+                    texture_format,
+                    colormap: EguiColormap::Rgba,
+                    masked: false,
+                },
+            );
+        }
+        *warmed_up = true;
+    }
+
+    let mut colormaps = vec![EguiColormap::Rgba];
+    for options in user_textures.options.values() {
+        if !colormaps.contains(&options.colormap) {
+            colormaps.push(options.colormap);
+        }
+    }
+
+    let mut egui_pipelines = HashMap::default();
+    for (window_id, window) in windows.iter() {
+        let Some(texture_format) = window.swap_chain_texture_format else {
+            continue;
+        };
+        for &colormap in &colormaps {
             let key = EguiPipelineKey {
-                texture_format: window.swap_chain_texture_format?.add_srgb_suffix(),
+                texture_format: texture_format.add_srgb_suffix(),
+                colormap,
+                masked: masks.contains(*window_id),
             };
             let pipeline_id = pipelines.specialize(&pipeline_cache, &egui_pipeline, key);
+            egui_pipelines.insert((*window_id, colormap), pipeline_id);
+        }
+    }
 
-            Some((*window_id, pipeline_id))
-        })
-        .collect();
-
-    commands.insert_resource(EguiPipelines(pipelines));
+    commands.insert_resource(EguiPipelines(egui_pipelines));
 }
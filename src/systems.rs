@@ -1,24 +1,30 @@
 use crate::{
-    EguiContext, EguiContextQuery, EguiInput, EguiMousePosition, EguiSettings, WindowSize,
+    EguiClock, EguiContext, EguiContextQuery, EguiCopyRequest, EguiDragReleased, EguiInput,
+    EguiInputEventsHook, EguiMousePosition, EguiPointerTransform, EguiRenderCapabilities,
+    EguiRenderOutput, EguiRenderWhenUnfocused, EguiSettings, EguiTexturesDeltaStats,
+    EguiUiActivity, EguiWindowActivity, WindowSize, MIN_MAX_TEXTURE_SIDE,
 };
-#[cfg(feature = "open_url")]
-use bevy::log;
 use bevy::{
     ecs::{
         event::EventWriter,
+        query::Has,
         system::{Local, Res, ResMut, SystemParam},
     },
     input::{
         keyboard::{KeyCode, KeyboardInput},
         mouse::{MouseButton, MouseButtonInput, MouseScrollUnit, MouseWheel},
         touch::TouchInput,
+        touchpad::TouchpadMagnify,
         ButtonState, Input,
     },
+    log,
+    math::Vec2,
     prelude::{Entity, EventReader, Query, Resource, Time},
     time::Real,
+    utils::{Entry, HashMap},
     window::{
-        CursorEntered, CursorLeft, CursorMoved, ReceivedCharacter, RequestRedraw, WindowCreated,
-        WindowFocused,
+        CursorEntered, CursorLeft, CursorMoved, ReceivedCharacter, RequestRedraw, Window,
+        WindowCreated, WindowFocused, WindowTheme, WindowThemeChanged,
     },
 };
 use std::marker::PhantomData;
@@ -31,6 +37,7 @@ pub struct InputEvents<'w, 's> {
     pub ev_cursor: EventReader<'w, 's, CursorMoved>,
     pub ev_mouse_button_input: EventReader<'w, 's, MouseButtonInput>,
     pub ev_mouse_wheel: EventReader<'w, 's, MouseWheel>,
+    pub ev_touchpad_magnify: EventReader<'w, 's, TouchpadMagnify>,
     pub ev_received_character: EventReader<'w, 's, ReceivedCharacter>,
     pub ev_keyboard_input: EventReader<'w, 's, KeyboardInput>,
     pub ev_window_focused: EventReader<'w, 's, WindowFocused>,
@@ -47,6 +54,7 @@ impl<'w, 's> InputEvents<'w, 's> {
         self.ev_cursor.read().last();
         self.ev_mouse_button_input.read().last();
         self.ev_mouse_wheel.read().last();
+        self.ev_touchpad_magnify.read().last();
         self.ev_received_character.read().last();
         self.ev_keyboard_input.read().last();
         self.ev_window_focused.read().last();
@@ -68,11 +76,36 @@ pub struct InputResources<'w, 's> {
     _marker: PhantomData<&'s ()>,
 }
 
+/// Tracks a key that is being held, to synthesize repeated key events.
+#[derive(Clone, Copy, Debug)]
+pub struct HeldKey {
+    /// Time (in seconds) when the key was pressed.
+    pub pressed_at: f64,
+    /// Time (in seconds) when the last repeated event was sent.
+    pub last_repeat: f64,
+    /// Whether the OS already sends repeated events for this key.
+    pub os_repeat: bool,
+}
+
+/// Tracks the touch that emulates the pointer, to apply [`EguiSettings::touch_drag_deadzone`].
+#[derive(Clone, Copy, Debug)]
+pub struct PointerTouchStart {
+    /// Position (in points) where the touch has started.
+    pub pos: egui::Pos2,
+    /// Time (in seconds) when the touch has started.
+    pub time: f64,
+    /// Whether the touch has left the deadzone, so its moves are sent to Egui.
+    pub is_dragging: bool,
+}
+
 #[allow(missing_docs)]
 #[derive(SystemParam)]
 pub struct ContextSystemParams<'w, 's> {
     pub focused_window: Local<'s, Option<Entity>>,
     pub pointer_touch_id: Local<'s, TouchId>,
+    pub pointer_touch_start: Local<'s, Option<PointerTouchStart>>,
+    pub held_keys: Local<'s, HashMap<egui::Key, HeldKey>>,
+    pub fixed_clock_elapsed: Local<'s, f64>,
     pub contexts: Query<'w, 's, EguiContextQuery>,
     #[system_param(ignore)]
     _marker: PhantomData<&'s ()>,
@@ -86,11 +119,21 @@ pub fn process_input_system(
     egui_settings: Res<EguiSettings>,
     mut egui_mouse_position: ResMut<EguiMousePosition>,
     time: Res<Time<Real>>,
+    egui_clock: Res<EguiClock>,
 ) {
+    let (now, delta_seconds) = match *egui_clock {
+        EguiClock::Real => (time.elapsed_seconds_f64(), time.delta_seconds()),
+        EguiClock::Fixed(step) => {
+            *context_params.fixed_clock_elapsed += step as f64;
+            (*context_params.fixed_clock_elapsed, step)
+        }
+    };
+
     // This is a workaround for Windows. For some reason, `WindowFocused` event isn't fired
     // when a window is created.
     if let Some(event) = input_events.ev_window_created.read().last() {
         *context_params.focused_window = Some(event.window);
+        context_params.held_keys.clear();
     }
 
     for event in input_events.ev_window_focused.read() {
@@ -99,6 +142,9 @@ pub fn process_input_system(
         } else {
             None
         };
+        // We won't receive release events for the keys that are released while a window is
+        // unfocused, so we stop repeating them.
+        context_params.held_keys.clear();
     }
 
     let shift = input_resources.keyboard_input.pressed(KeyCode::ShiftLeft)
@@ -153,20 +199,32 @@ pub fn process_input_system(
         // window this exact frame, so we are safe to ignore all `CursorMoved` events for the window
         // that has been left.
         if cursor_left_window != Some(cursor_moved.window) {
-            let scale_factor = egui_settings.scale_factor as f32;
-            let mouse_position: (f32, f32) = (cursor_moved.position / scale_factor).into();
             let mut context = context_params
                 .contexts
                 .get_mut(cursor_moved.window)
                 .unwrap();
-            egui_mouse_position.0 = Some((cursor_moved.window, mouse_position.into()));
-            context
-                .egui_input
-                .events
-                .push(egui::Event::PointerMoved(egui::pos2(
-                    mouse_position.0,
-                    mouse_position.1,
-                )));
+            let window_scale_factor = context.window.scale_factor() as f32;
+            let mouse_position = egui_pointer_position(
+                cursor_moved.position * window_scale_factor,
+                pixels_per_point(&context.egui_input, window_scale_factor, &egui_settings),
+                context.pointer_transform,
+                egui_settings.viewport_rect,
+            );
+            if let Some(mouse_position) = mouse_position {
+                egui_mouse_position.0 = Some((cursor_moved.window, mouse_position.to_vec2()));
+                context
+                    .egui_input
+                    .events
+                    .push(egui::Event::PointerMoved(mouse_position));
+            } else {
+                // The pointer transform has rejected the position, so we treat the pointer as
+                // if it was outside the window.
+                if matches!(egui_mouse_position.0, Some((window, _)) if window == cursor_moved.window)
+                {
+                    egui_mouse_position.0 = None;
+                }
+                context.egui_input.events.push(egui::Event::PointerGone);
+            }
         }
     }
 
@@ -205,7 +263,7 @@ pub fn process_input_system(
                     delta *= 50.0;
                 }
 
-                if ctrl || mac_cmd {
+                if (ctrl || mac_cmd) && egui_settings.zoom_with_ctrl_scroll {
                     // Treat as zoom instead.
                     let factor = (delta.y / 200.0).exp();
                     events.push(egui::Event::Zoom(factor));
@@ -217,6 +275,11 @@ pub fn process_input_system(
                     events.push(egui::Event::Scroll(delta));
                 }
             }
+
+            for event in input_events.ev_touchpad_magnify.read() {
+                // Same as `egui-winit` does for `WindowEvent::TouchpadMagnify`.
+                events.push(egui::Event::Zoom(event.0.exp()));
+            }
         }
     }
 
@@ -232,12 +295,16 @@ pub fn process_input_system(
         }
     }
 
-    if let Some(mut focused_input) = context_params
+    if let Some((mut focused_input, window_scale_factor, pointer_transform)) = context_params
         .focused_window
         .as_ref()
         .and_then(|window_id| {
             if let Ok(context) = context_params.contexts.get_mut(*window_id) {
-                Some(context.egui_input)
+                Some((
+                    context.egui_input,
+                    context.window.scale_factor() as f32,
+                    context.pointer_transform,
+                ))
             } else {
                 None
             }
@@ -249,10 +316,31 @@ pub fn process_input_system(
                     ButtonState::Pressed => true,
                     ButtonState::Released => false,
                 };
+                // If we receive a press event for a key that is already held, this is a repeat
+                // sent by the OS, so we don't need to synthesize repeats for that key.
+                let repeat = if pressed {
+                    match context_params.held_keys.entry(key) {
+                        Entry::Occupied(mut entry) => {
+                            entry.get_mut().os_repeat = true;
+                            true
+                        }
+                        Entry::Vacant(entry) => {
+                            entry.insert(HeldKey {
+                                pressed_at: now,
+                                last_repeat: now,
+                                os_repeat: false,
+                            });
+                            false
+                        }
+                    }
+                } else {
+                    context_params.held_keys.remove(&key);
+                    false
+                };
                 let egui_event = egui::Event::Key {
                     key,
                     pressed,
-                    repeat: false,
+                    repeat,
                     modifiers,
                 };
                 focused_input.events.push(egui_event);
@@ -269,8 +357,21 @@ pub fn process_input_system(
                             focused_input.events.push(egui::Event::Cut);
                         }
                         egui::Key::V => {
-                            if let Some(contents) = input_resources.egui_clipboard.get_contents() {
-                                focused_input.events.push(egui::Event::Text(contents))
+                            let contents = if egui_settings.use_system_clipboard {
+                                input_resources.egui_clipboard.get_contents()
+                            } else {
+                                input_resources
+                                    .egui_clipboard
+                                    .paste_contents()
+                                    .map(str::to_owned)
+                            };
+                            if let Some(contents) = contents {
+                                focused_input
+                                    .events
+                                    .push(egui::Event::Text(normalize_pasted_text(
+                                        &contents,
+                                        egui_settings.strip_pasted_control_chars,
+                                    )))
                             }
                         }
                         _ => {}
@@ -279,9 +380,33 @@ pub fn process_input_system(
             }
         }
 
+        let repeat_delay = egui_settings.key_repeat_delay as f64;
+        let repeat_interval = egui_settings.key_repeat_interval as f64;
+        for (key, held_key) in context_params.held_keys.iter_mut() {
+            if held_key.os_repeat
+                || now - held_key.pressed_at < repeat_delay
+                || now - held_key.last_repeat < repeat_interval
+            {
+                continue;
+            }
+            held_key.last_repeat = now;
+            focused_input.events.push(egui::Event::Key {
+                key: *key,
+                pressed: true,
+                repeat: true,
+                modifiers,
+            });
+        }
+
         for touch in input_events.ev_touch.read() {
-            let scale_factor = egui_settings.scale_factor as f32;
-            let touch_position: (f32, f32) = (touch.position / scale_factor).into();
+            let Some(touch_position) = egui_pointer_position(
+                touch.position * window_scale_factor,
+                pixels_per_point(&focused_input, window_scale_factor, &egui_settings),
+                pointer_transform,
+                egui_settings.viewport_rect,
+            ) else {
+                continue;
+            };
 
             // Emit touch event
             focused_input.events.push(egui::Event::Touch {
@@ -293,7 +418,7 @@ pub fn process_input_system(
                     bevy::input::touch::TouchPhase::Ended => egui::TouchPhase::End,
                     bevy::input::touch::TouchPhase::Canceled => egui::TouchPhase::Cancel,
                 },
-                pos: egui::pos2(touch_position.0, touch_position.1),
+                pos: touch_position,
                 force: match touch.force {
                     Some(bevy::input::touch::ForceTouch::Normalized(force)) => Some(force as f32),
                     Some(bevy::input::touch::ForceTouch::Calibrated {
@@ -314,33 +439,49 @@ pub fn process_input_system(
                 match touch.phase {
                     bevy::input::touch::TouchPhase::Started => {
                         context_params.pointer_touch_id.0 = Some(touch.id);
+                        *context_params.pointer_touch_start = Some(PointerTouchStart {
+                            pos: touch_position,
+                            time: now,
+                            is_dragging: egui_settings.touch_drag_deadzone <= 0.0,
+                        });
                         // First move the pointer to the right location
                         focused_input
                             .events
-                            .push(egui::Event::PointerMoved(egui::pos2(
-                                touch_position.0,
-                                touch_position.1,
-                            )));
+                            .push(egui::Event::PointerMoved(touch_position));
                         // Then do mouse button input
                         focused_input.events.push(egui::Event::PointerButton {
-                            pos: egui::pos2(touch_position.0, touch_position.1),
+                            pos: touch_position,
                             button: egui::PointerButton::Primary,
                             pressed: true,
                             modifiers,
                         });
                     }
                     bevy::input::touch::TouchPhase::Moved => {
+                        if let Some(start) = context_params.pointer_touch_start.as_mut() {
+                            // Small moves of a fresh touch are withheld, so that Egui doesn't
+                            // take a slightly shaky tap for a drag.
+                            if !start.is_dragging
+                                && start.pos.distance(touch_position)
+                                    <= egui_settings.touch_drag_deadzone
+                                && now - start.time < egui_settings.touch_tap_timeout as f64
+                            {
+                                continue;
+                            }
+                            start.is_dragging = true;
+                        }
                         focused_input
                             .events
-                            .push(egui::Event::PointerMoved(egui::pos2(
-                                touch_position.0,
-                                touch_position.1,
-                            )));
+                            .push(egui::Event::PointerMoved(touch_position));
                     }
                     bevy::input::touch::TouchPhase::Ended => {
                         context_params.pointer_touch_id.0 = None;
+                        // A tap is released where it was pressed.
+                        let release_position = match context_params.pointer_touch_start.take() {
+                            Some(start) if !start.is_dragging => start.pos,
+                            _ => touch_position,
+                        };
                         focused_input.events.push(egui::Event::PointerButton {
-                            pos: egui::pos2(touch_position.0, touch_position.1),
+                            pos: release_position,
                             button: egui::PointerButton::Primary,
                             pressed: false,
                             modifiers,
@@ -349,6 +490,7 @@ pub fn process_input_system(
                     }
                     bevy::input::touch::TouchPhase::Canceled => {
                         context_params.pointer_touch_id.0 = None;
+                        *context_params.pointer_touch_start = None;
                         focused_input.events.push(egui::Event::PointerGone);
                     }
                 }
@@ -359,7 +501,7 @@ pub fn process_input_system(
     }
 
     for mut context in context_params.contexts.iter_mut() {
-        context.egui_input.predicted_dt = time.delta_seconds();
+        context.egui_input.predicted_dt = delta_seconds;
     }
 
     // In some cases, we may skip certain events. For example, we ignore `ReceivedCharacter` events
@@ -371,8 +513,48 @@ pub fn process_input_system(
 pub fn update_window_contexts_system(
     mut context_params: ContextSystemParams,
     egui_settings: Res<EguiSettings>,
+    egui_render_capabilities: Option<Res<EguiRenderCapabilities>>,
+    mut egui_mouse_position: ResMut<EguiMousePosition>,
+    mut ev_window_theme_changed: EventReader<WindowThemeChanged>,
 ) {
+    let theme_changes: HashMap<Entity, WindowTheme> = ev_window_theme_changed
+        .read()
+        .map(|event| (event.window, event.theme))
+        .collect();
+
+    let device_max_texture_side =
+        egui_render_capabilities.map(|capabilities| capabilities.max_texture_side);
+    // Egui panics if its font atlas is narrower.
+    let settings_max_texture_side = egui_settings
+        .max_texture_side
+        .map(|max_texture_side| max_texture_side.max(MIN_MAX_TEXTURE_SIDE));
+    let max_texture_side = match (settings_max_texture_side, device_max_texture_side) {
+        (Some(max_texture_side), Some(device_max_texture_side)) => {
+            Some(max_texture_side.min(device_max_texture_side))
+        }
+        (max_texture_side, device_max_texture_side) => max_texture_side.or(device_max_texture_side),
+    };
+
     for mut context in context_params.contexts.iter_mut() {
+        // `Window::window_theme` isn't updated when the OS theme changes, it's only used
+        // until the first change event. Only the changes replace the visuals, so that the ones
+        // set by the app at startup are kept.
+        let theme_change = theme_changes
+            .get(&context.window_entity)
+            .copied()
+            .filter(|&theme| context.ctx.system_theme != Some(theme));
+        if let Some(theme) = theme_change {
+            context.ctx.system_theme = Some(theme);
+            if egui_settings.follow_system_theme {
+                context.ctx.get_mut().set_visuals(match theme {
+                    WindowTheme::Light => egui::Visuals::light(),
+                    WindowTheme::Dark => egui::Visuals::dark(),
+                });
+            }
+        } else if context.ctx.system_theme.is_none() {
+            context.ctx.system_theme = context.window.window_theme;
+        }
+
         let new_window_size = WindowSize::new(
             context.window.physical_width() as f32,
             context.window.physical_height() as f32,
@@ -385,75 +567,304 @@ pub fn update_window_contexts_system(
             / new_window_size.scale_factor
             / egui_settings.scale_factor as f32;
 
-        if width < 1.0 || height < 1.0 {
+        let mut screen_rect =
+            egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(width, height));
+        if let Some(viewport_rect) = egui_settings.viewport_rect {
+            screen_rect = screen_rect.intersect(viewport_rect);
+        }
+        if screen_rect.width() < 1.0 || screen_rect.height() < 1.0 {
             continue;
         }
 
-        context.egui_input.screen_rect = Some(egui::Rect::from_min_max(
-            egui::pos2(0.0, 0.0),
-            egui::pos2(width, height),
-        ));
+        context.egui_input.screen_rect = Some(screen_rect);
 
-        context.egui_input.pixels_per_point =
-            Some(new_window_size.scale_factor * egui_settings.scale_factor as f32);
+        let pixels_per_point = new_window_size.scale_factor * egui_settings.scale_factor as f32;
+        context.egui_input.pixels_per_point = Some(pixels_per_point);
+        context.egui_input.max_texture_side = max_texture_side;
+
+        // The last known mouse position is stored in points, so it has to follow scale changes
+        // (e.g. when a window is moved to another monitor or `EguiSettings::scale_factor` is
+        // animated), otherwise button events that come before the next `CursorMoved` would be
+        // reported at a wrong position. Egui's pixels per point are the ones of the last frame.
+        let previous_pixels_per_point = context.ctx.get_mut().pixels_per_point();
+        if context.window_size.scale_factor != 0.0 && previous_pixels_per_point != pixels_per_point
+        {
+            if let Some((window, position)) = egui_mouse_position.0.as_mut() {
+                if *window == context.window_entity {
+                    *position *= previous_pixels_per_point / pixels_per_point;
+                }
+            }
+        }
 
         *context.window_size = new_window_size;
     }
 }
 
 /// Marks frame start for Egui.
-pub fn begin_frame_system(mut contexts: Query<(&mut EguiContext, &mut EguiInput)>) {
-    for (mut ctx, mut egui_input) in contexts.iter_mut() {
+#[allow(clippy::type_complexity)]
+pub fn begin_frame_system(
+    egui_settings: Res<EguiSettings>,
+    mut contexts: Query<(
+        &mut EguiContext,
+        &mut EguiInput,
+        &mut EguiRenderOutput,
+        &Window,
+        Has<EguiRenderWhenUnfocused>,
+        Option<&mut EguiInputEventsHook>,
+    )>,
+) {
+    for (mut ctx, mut egui_input, mut render_output, window, render_when_unfocused, events_hook) in
+        contexts.iter_mut()
+    {
+        if ctx.frame_in_progress {
+            // The previous frame was interrupted (e.g. `EguiSet::ProcessOutput` didn't run),
+            // end it so that the context doesn't get stuck in it.
+            log::warn!("The previous Egui frame wasn't ended, discarding its output");
+            let _ = ctx.get_mut().end_frame();
+        }
+
+        if !egui_settings.render_when_unfocused && !render_when_unfocused && !window.focused {
+            egui_input.events.clear();
+            ctx.frame_skipped = true;
+            continue;
+        }
+        if std::mem::take(&mut ctx.frame_skipped) {
+            // Discards what UI systems have drawn while the frame was skipped, so that the first
+            // frame after focusing the window contains only its own shapes.
+            let ctx = ctx.get_mut();
+            ctx.begin_frame(egui::RawInput {
+                screen_rect: egui_input.screen_rect,
+                pixels_per_point: egui_input.pixels_per_point,
+                max_texture_side: egui_input.max_texture_side,
+                ..Default::default()
+            });
+            let full_output = ctx.end_frame();
+            render_output
+                .textures_delta
+                .append(full_output.textures_delta);
+        }
+
+        if let Some(mut events_hook) = events_hook {
+            events_hook.rewrite(&mut egui_input.events);
+        }
+        // The grab is applied last, so that the events pushed by app systems (or the hook) after
+        // `EguiSet::ProcessInput` don't get past it either.
+        if ctx.input_grabbed {
+            egui_input.events = grabbed_input_events(ctx.get_mut());
+            egui_input.modifiers = egui::Modifiers::NONE;
+        }
         ctx.get_mut().begin_frame(egui_input.take());
+        ctx.frame_in_progress = true;
+        ctx.has_run_frames = true;
     }
 }
 
+/// Releases the buttons and keys Egui considers pressed and makes the pointer leave the window,
+/// in place of the input withheld from a grabbed context (see [`EguiContexts::grab_input`]).
+///
+/// [`EguiContexts::grab_input`]: crate::EguiContexts::grab_input
+fn grabbed_input_events(ctx: &egui::Context) -> Vec<egui::Event> {
+    ctx.input(|input| {
+        let pos = input.pointer.latest_pos().unwrap_or_default();
+        let mut events: Vec<_> = [
+            egui::PointerButton::Primary,
+            egui::PointerButton::Secondary,
+            egui::PointerButton::Middle,
+            egui::PointerButton::Extra1,
+            egui::PointerButton::Extra2,
+        ]
+        .into_iter()
+        .filter(|&button| input.pointer.button_down(button))
+        .map(|button| egui::Event::PointerButton {
+            pos,
+            button,
+            pressed: false,
+            modifiers: egui::Modifiers::NONE,
+        })
+        .collect();
+        events.extend(input.keys_down.iter().map(|&key| egui::Event::Key {
+            key,
+            pressed: false,
+            repeat: false,
+            modifiers: egui::Modifiers::NONE,
+        }));
+        events.push(egui::Event::PointerGone);
+        events
+    })
+}
+
+/// The cursor icon the app had set before Egui took over the cursor of a window.
+#[derive(Clone, Copy, Debug)]
+pub struct AppCursorIcon {
+    /// The icon to restore when Egui stops using the cursor.
+    pub app_icon: bevy::window::CursorIcon,
+    /// The icon Egui has set the last time.
+    pub egui_icon: bevy::window::CursorIcon,
+}
+
 /// Reads Egui output.
+///
+/// The window cursor icon is shared with the app: Egui's cursor (from
+/// [`egui::PlatformOutput::cursor_icon`]) is applied only while the pointer is over an Egui area,
+/// is being used by Egui (e.g. dragging a slider outside of its window), or Egui requests
+/// a non-default icon. When Egui stops requesting it, the icon the app had set is restored. Icons
+/// set by the app while Egui owns the cursor are remembered and restored the same way.
+#[allow(clippy::too_many_arguments)]
 pub fn process_output_system(
-    #[cfg_attr(not(feature = "open_url"), allow(unused_variables))] egui_settings: Res<
-        EguiSettings,
-    >,
+    egui_settings: Res<EguiSettings>,
     mut contexts: Query<EguiContextQuery>,
     #[cfg(feature = "manage_clipboard")] mut egui_clipboard: ResMut<crate::EguiClipboard>,
     mut event: EventWriter<RequestRedraw>,
-    #[cfg(windows)] mut last_cursor_icon: Local<bevy::utils::HashMap<Entity, egui::CursorIcon>>,
+    mut drag_released: EventWriter<EguiDragReleased>,
+    mut copy_requests: EventWriter<EguiCopyRequest>,
+    mut egui_ui_activity: ResMut<EguiUiActivity>,
+    mut app_cursor_icons: Local<HashMap<Entity, AppCursorIcon>>,
 ) {
     for mut context in contexts.iter_mut() {
+        // The frame may be skipped (see `EguiSettings::render_when_unfocused`), or the context
+        // may have been replaced after the frame had begun.
+        if !context.ctx.frame_in_progress {
+            continue;
+        }
+        context.ctx.frame_in_progress = false;
         let ctx = context.ctx.get_mut();
         let full_output = ctx.end_frame();
+
+        // A drag that is released this frame is still reported by Egui's memory until the next
+        // frame begins.
+        let drag_released_pos = ctx.input(|input| {
+            input
+                .pointer
+                .interact_pos()
+                .filter(|_| input.pointer.any_released())
+        });
+        if let Some(pos) = drag_released_pos {
+            if ctx.memory(|memory| memory.is_anything_being_dragged())
+                && !ctx.is_pointer_over_area()
+            {
+                drag_released.send(EguiDragReleased {
+                    window: context.window_entity,
+                    pos: Vec2::new(pos.x, pos.y) * egui_settings.scale_factor as f32,
+                });
+            }
+        }
+
         let egui::FullOutput {
             platform_output,
             shapes,
             textures_delta,
             repaint_after,
         } = full_output;
+        let feathering_size_in_pixels = context
+            .feathering
+            .map_or(egui_settings.feathering_size_in_pixels, |feathering| {
+                feathering.0
+            });
+        ctx.tessellation_options_mut(|options| {
+            options.feathering = feathering_size_in_pixels > 0.0;
+            options.feathering_size_in_pixels = feathering_size_in_pixels;
+        });
         let paint_jobs = ctx.tessellate(shapes);
+        let painted_rect = paint_jobs
+            .iter()
+            .map(|clipped_primitive| {
+                let bounds = match &clipped_primitive.primitive {
+                    egui::epaint::Primitive::Mesh(mesh) => mesh.calc_bounds(),
+                    egui::epaint::Primitive::Callback(callback) => callback.rect,
+                };
+                bounds.intersect(clipped_primitive.clip_rect)
+            })
+            .filter(|rect| rect.is_positive())
+            .fold(egui::Rect::NOTHING, |painted_rect, rect| {
+                painted_rect.union(rect)
+            });
+        egui_ui_activity.insert(
+            context.window_entity,
+            EguiWindowActivity {
+                primitive_count: paint_jobs.len(),
+                painted_rect,
+                screen_rect: ctx.screen_rect(),
+                wants_pointer_input: ctx.wants_pointer_input(),
+                wants_keyboard_input: ctx.wants_keyboard_input(),
+            },
+        );
+
+        let textures_delta_stats = EguiTexturesDeltaStats::new(&textures_delta);
+        let font_atlas_uploaded = textures_delta.set.iter().any(|(texture_id, image_delta)| {
+            *texture_id == egui::TextureId::default() && image_delta.pos.is_none()
+        });
+        // Egui reports an overflowed atlas as completely full, and clears it when the next frame
+        // begins.
+        let font_atlas_overflowed = ctx.fonts(|fonts| fonts.font_atlas_fill_ratio() >= 1.0);
 
         context.render_output.paint_jobs = paint_jobs;
         context.render_output.textures_delta.append(textures_delta);
 
         context.egui_output.platform_output = platform_output.clone();
 
-        #[cfg(feature = "manage_clipboard")]
         if !platform_output.copied_text.is_empty() {
-            egui_clipboard.set_contents(&platform_output.copied_text);
+            #[cfg(feature = "manage_clipboard")]
+            if egui_settings.use_system_clipboard {
+                egui_clipboard.set_contents(&platform_output.copied_text);
+            }
+            copy_requests.send(EguiCopyRequest {
+                window: context.window_entity,
+                text: platform_output.copied_text.clone(),
+            });
         }
 
-        let mut set_icon = || {
-            context.window.cursor.icon = egui_to_winit_cursor_icon(platform_output.cursor_icon)
+        let egui_owns_cursor = ctx.is_pointer_over_area()
+            || ctx.is_using_pointer()
+            || platform_output.cursor_icon != egui::CursorIcon::Default;
+
+        context.ctx.textures_delta_stats = textures_delta_stats;
+        if font_atlas_uploaded {
+            context.ctx.font_atlas_version += 1;
+        }
+        if font_atlas_overflowed && !context.ctx.font_atlas_overflowed {
+            log::warn!(
+                "The Egui font atlas of window {:?} overflowed, some text may be drawn garbled \
+                (see `EguiSettings::max_texture_side`)",
+                context.window_entity
+            );
+        }
+        context.ctx.font_atlas_overflowed = font_atlas_overflowed;
+        let window_icon = context.window.cursor.icon;
+        if egui_owns_cursor {
+            let egui_icon = egui_to_winit_cursor_icon(platform_output.cursor_icon)
                 .unwrap_or(bevy::window::CursorIcon::Default);
-        };
+            let app_cursor_icon =
+                app_cursor_icons
+                    .entry(context.window_entity)
+                    .or_insert(AppCursorIcon {
+                        app_icon: window_icon,
+                        egui_icon,
+                    });
+            if window_icon != app_cursor_icon.egui_icon {
+                // The app has changed the icon since the last frame.
+                app_cursor_icon.app_icon = window_icon;
+            }
+            app_cursor_icon.egui_icon = egui_icon;
+            // Comparing first, as writing marks the window as changed and makes Bevy update
+            // the OS cursor (which flickers on Windows).
+            if window_icon != egui_icon {
+                context.window.cursor.icon = egui_icon;
+            }
+        } else if let Some(app_cursor_icon) = app_cursor_icons.remove(&context.window_entity) {
+            if window_icon == app_cursor_icon.egui_icon {
+                context.window.cursor.icon = app_cursor_icon.app_icon;
+            }
+        }
 
-        #[cfg(windows)]
-        {
-            let last_cursor_icon = last_cursor_icon.entry(context.window_entity).or_default();
-            if *last_cursor_icon != platform_output.cursor_icon {
-                set_icon();
-                *last_cursor_icon = platform_output.cursor_icon;
+        // Lets the OS position IME candidate windows next to the text cursor. Bevy expects
+        // the position in logical pixels.
+        if let Some(text_cursor_pos) = platform_output.text_cursor_pos {
+            let ime_position =
+                Vec2::new(text_cursor_pos.x, text_cursor_pos.y) * egui_settings.scale_factor as f32;
+            if context.window.ime_position != ime_position {
+                context.window.ime_position = ime_position;
             }
         }
-        #[cfg(not(windows))]
-        set_icon();
 
         if repaint_after.is_zero() {
             event.send(RequestRedraw)
@@ -478,6 +889,53 @@ pub fn process_output_system(
             }
         }
     }
+
+    // Forgets the cursor icons of closed windows.
+    app_cursor_icons.retain(|&window_id, _| contexts.contains(window_id));
+}
+
+/// Converts `\r\n` and `\r` line endings of pasted text to `\n` and optionally removes control
+/// characters other than newlines and tabs.
+#[cfg(feature = "manage_clipboard")]
+pub(crate) fn normalize_pasted_text(text: &str, strip_control_chars: bool) -> String {
+    text.replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .chars()
+        .filter(|&c| !strip_control_chars || !c.is_control() || c == '\n' || c == '\t')
+        .collect()
+}
+
+/// The pixels per point the current frame of a context is laid out with (set by
+/// [`update_window_contexts_system`]).
+fn pixels_per_point(
+    egui_input: &EguiInput,
+    window_scale_factor: f32,
+    egui_settings: &EguiSettings,
+) -> f32 {
+    egui_input
+        .pixels_per_point
+        .unwrap_or(window_scale_factor * egui_settings.scale_factor as f32)
+}
+
+/// Converts a pointer position in physical pixels to Egui points, applying
+/// [`EguiPointerTransform`] if a window has one. Positions outside of
+/// [`EguiSettings::viewport_rect`] are rejected.
+fn egui_pointer_position(
+    physical_position: Vec2,
+    pixels_per_point: f32,
+    pointer_transform: Option<&EguiPointerTransform>,
+    viewport_rect: Option<egui::Rect>,
+) -> Option<egui::Pos2> {
+    let position = match pointer_transform {
+        Some(pointer_transform) => pointer_transform.transform(physical_position)?,
+        None => {
+            let position = physical_position / pixels_per_point;
+            egui::pos2(position.x, position.y)
+        }
+    };
+    viewport_rect
+        .map_or(true, |viewport_rect| viewport_rect.contains(position))
+        .then_some(position)
 }
 
 fn egui_to_winit_cursor_icon(cursor_icon: egui::CursorIcon) -> Option<bevy::window::CursorIcon> {